@@ -0,0 +1,38 @@
+//! Deny-pattern matching: one `RegexSet` per category vs. compiling and
+//! testing each pattern in turn. Run with `cargo bench`.
+
+use claude_permission_hook::config::default_config;
+use claude_permission_hook::permission::is_auto_denied;
+use regex::Regex;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 200;
+
+fn main() {
+    let mut config = default_config();
+    // An enterprise-sized deny list
+    for i in 0..300 {
+        config.auto_deny.bash_patterns.push(format!(r"internal-tool-{}\s+--(drop|purge)", i));
+    }
+    let patterns = config.auto_deny.bash_patterns.clone();
+    let input = serde_json::json!({"command": "cargo build --release && git status"});
+
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        let matched = patterns
+            .iter()
+            .any(|p| Regex::new(p).map(|re| re.is_match("cargo build --release")).unwrap_or(false));
+        assert!(!matched);
+    }
+    let per_pattern = started.elapsed() / ITERATIONS;
+
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert!(is_auto_denied(&config, "Bash", &input, "").is_none());
+    }
+    let set = started.elapsed() / ITERATIONS;
+
+    println!("{} patterns", patterns.len());
+    println!("per-pattern regex:  {:?} per command", per_pattern);
+    println!("cached RegexSet:    {:?} per command", set);
+}
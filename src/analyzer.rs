@@ -1,6 +1,6 @@
 //! Status analyzer - State machine for determining notification status from transcripts
 
-use crate::config::Config;
+use crate::config::{AnalyzerConfig, Config};
 use crate::jsonl;
 
 /// Notification status types
@@ -27,6 +27,21 @@ impl Status {
             Status::Unknown => "unknown",
         }
     }
+
+    /// Parse a status from its `as_str` name
+    pub fn from_name(name: &str) -> Option<Status> {
+        [
+            Status::TaskComplete,
+            Status::ReviewComplete,
+            Status::Question,
+            Status::PlanReady,
+            Status::SessionLimitReached,
+            Status::ApiError,
+            Status::Unknown,
+        ]
+        .into_iter()
+        .find(|s| s.as_str() == name)
+    }
 }
 
 impl std::fmt::Display for Status {
@@ -49,14 +64,26 @@ const PASSIVE_TOOLS: &[&str] = &[
     "WebFetch", "WebSearch", "AskFollowupQuestion"
 ];
 
+/// Whether a tool is in a category: listed in `custom`, or a default that
+/// wasn't moved to another category (`elsewhere`) by the config
+fn in_category(tool: &str, custom: &[String], defaults: &[&str], elsewhere: [&[String]; 2]) -> bool {
+    custom.iter().any(|t| t == tool)
+        || (defaults.contains(&tool) && !elsewhere.iter().any(|list| list.iter().any(|t| t == tool)))
+}
+
 /// Check if a tool is an active tool (makes changes)
-fn is_active_tool(tool: &str) -> bool {
-    ACTIVE_TOOLS.contains(&tool)
+fn is_active_tool(tool: &str, config: &AnalyzerConfig) -> bool {
+    in_category(tool, &config.active_tools, ACTIVE_TOOLS, [&config.read_like_tools, &config.passive_tools])
 }
 
 /// Check if a tool is a read-like tool
-fn is_read_like_tool(tool: &str) -> bool {
-    READ_LIKE_TOOLS.contains(&tool)
+fn is_read_like_tool(tool: &str, config: &AnalyzerConfig) -> bool {
+    in_category(tool, &config.read_like_tools, READ_LIKE_TOOLS, [&config.active_tools, &config.passive_tools])
+}
+
+/// Check if a tool is a passive tool (neither changes nor reads the project)
+pub fn is_passive_tool(tool: &str, config: &AnalyzerConfig) -> bool {
+    in_category(tool, &config.passive_tools, PASSIVE_TOOLS, [&config.active_tools, &config.read_like_tools])
 }
 
 /// Get status for PreToolUse event
@@ -90,16 +117,16 @@ pub fn analyze_transcript(transcript_path: &str, config: &Config) -> Result<Stat
         return Ok(Status::Unknown);
     }
 
-    // Get recent assistant messages (after last user message, max 15)
-    let recent_messages = jsonl::get_recent_assistant_messages(&messages, 15);
+    // Get recent assistant messages (after last user message, up to the configured window)
+    let recent_messages = jsonl::get_recent_assistant_messages(&messages, config.analyzer.recent_window());
 
     if recent_messages.is_empty() {
         return Ok(Status::Unknown);
     }
 
-    // Priority 1: Check for session limit in last 3 assistant messages
-    let last_3: Vec<_> = recent_messages.iter().rev().take(3).collect();
-    for msg in &last_3 {
+    // Priority 1: Check for session limit in the last few assistant messages
+    let tail: Vec<_> = recent_messages.iter().rev().take(config.analyzer.tail_check()).collect();
+    for msg in &tail {
         let text = msg.get_text();
         if check_session_limit(&text) {
             return Ok(Status::SessionLimitReached);
@@ -107,13 +134,24 @@ pub fn analyze_transcript(transcript_path: &str, config: &Config) -> Result<Stat
     }
 
     // Priority 2: Check for API 401 error
-    for msg in &last_3 {
+    for msg in &tail {
         let text = msg.get_text();
         if check_api_error(&text) {
             return Ok(Status::ApiError);
         }
     }
 
+    // Optional: the turn ended on a plain-text question with no change (or plan) after it
+    if config.analyzer.detect_text_questions {
+        if let Some(last) = recent_messages.last() {
+            let asks = last.get_text().trim_end().ends_with('?');
+            let tools = last.get_tools();
+            if asks && !tools.iter().any(|t| t == "ExitPlanMode" || is_active_tool(t, &config.analyzer)) {
+                return Ok(Status::Question);
+            }
+        }
+    }
+
     // Collect all tools from recent messages
     let mut all_tools: Vec<String> = Vec::new();
     let mut total_text_length = 0;
@@ -154,18 +192,18 @@ pub fn analyze_transcript(transcript_path: &str, config: &Config) -> Result<Stat
     }
 
     // Check for active tools
-    let has_active_tool = all_tools.iter().any(|t| is_active_tool(t));
+    let has_active_tool = all_tools.iter().any(|t| is_active_tool(t, &config.analyzer));
 
     // Priority 6: Review detection (read-like tools, no active tools, long text)
-    if !has_active_tool {
-        let has_read_like = all_tools.iter().any(|t| is_read_like_tool(t));
-        if has_read_like && total_text_length > 200 {
+    if !has_active_tool && config.analyzer.review_complete_enabled {
+        let has_read_like = all_tools.iter().any(|t| is_read_like_tool(t, &config.analyzer));
+        if has_read_like && total_text_length > config.analyzer.review_min_text_length {
             return Ok(Status::ReviewComplete);
         }
     }
 
     // Priority 7: Active tool as last tool
-    if is_active_tool(last_tool) {
+    if is_active_tool(last_tool, &config.analyzer) {
         return Ok(Status::TaskComplete);
     }
 
@@ -234,19 +272,74 @@ mod tests {
 
     #[test]
     fn test_is_active_tool() {
-        assert!(is_active_tool("Write"));
-        assert!(is_active_tool("Bash"));
-        assert!(is_active_tool("Edit"));
-        assert!(!is_active_tool("Read"));
-        assert!(!is_active_tool("Glob"));
+        let config = AnalyzerConfig::default();
+        assert!(is_active_tool("Write", &config));
+        assert!(is_active_tool("Bash", &config));
+        assert!(is_active_tool("Edit", &config));
+        assert!(!is_active_tool("Read", &config));
+        assert!(!is_active_tool("Glob", &config));
     }
 
     #[test]
     fn test_is_read_like_tool() {
-        assert!(is_read_like_tool("Read"));
-        assert!(is_read_like_tool("Grep"));
-        assert!(is_read_like_tool("Glob"));
-        assert!(!is_read_like_tool("Write"));
+        let config = AnalyzerConfig::default();
+        assert!(is_read_like_tool("Read", &config));
+        assert!(is_read_like_tool("Grep", &config));
+        assert!(is_read_like_tool("Glob", &config));
+        assert!(!is_read_like_tool("Write", &config));
+    }
+
+    #[test]
+    fn test_custom_tool_categories() {
+        let config = AnalyzerConfig {
+            active_tools: vec!["mcp__db__migrate".into()],
+            passive_tools: vec!["Bash".into()],
+            ..Default::default()
+        };
+        assert!(is_active_tool("mcp__db__migrate", &config));
+        assert!(!is_passive_tool("mcp__db__migrate", &config));
+        // Listing a default tool elsewhere moves it
+        assert!(!is_active_tool("Bash", &config));
+        assert!(is_passive_tool("Bash", &config));
+        assert!(is_passive_tool("WebSearch", &config));
+    }
+
+    #[test]
+    fn test_analyze_text_question() {
+        let file = create_test_transcript(&[
+            ("user", &[], "Add a cache"),
+            ("assistant", &["Read"], "There are two ways to do this. Which option do you prefer?"),
+        ]);
+        let path = file.path().to_str().unwrap();
+
+        let mut config = Config::default();
+        assert_eq!(analyze_transcript(path, &config).unwrap(), Status::TaskComplete);
+
+        config.analyzer.detect_text_questions = true;
+        assert_eq!(analyze_transcript(path, &config).unwrap(), Status::Question);
+
+        // A change in the same message means the work went ahead anyway
+        let edited = create_test_transcript(&[
+            ("user", &[], "Add a cache"),
+            ("assistant", &["Edit"], "Done. Want me to add tests too?"),
+        ]);
+        assert_eq!(analyze_transcript(edited.path().to_str().unwrap(), &config).unwrap(), Status::TaskComplete);
+    }
+
+    #[test]
+    fn test_analyze_custom_active_tool() {
+        let file = create_test_transcript(&[
+            ("user", &[], "Migrate the database"),
+            ("assistant", &["Read", "mcp__db__migrate"], &"a".repeat(250)),
+        ]);
+        let path = file.path().to_str().unwrap();
+
+        // Unclassified, the MCP tool doesn't count as a change, so this reads as a review
+        let mut config = Config::default();
+        assert_eq!(analyze_transcript(path, &config).unwrap(), Status::ReviewComplete);
+
+        config.analyzer.active_tools = vec!["mcp__db__migrate".into()];
+        assert_eq!(analyze_transcript(path, &config).unwrap(), Status::TaskComplete);
     }
 
     #[test]
@@ -362,6 +455,77 @@ mod tests {
         assert_eq!(status, Status::TaskComplete);
     }
 
+    #[test]
+    fn test_analyze_review_threshold_boundary() {
+        let mut config = Config::default();
+        config.analyzer.review_min_text_length = 200;
+
+        let short = create_test_transcript(&[
+            ("user", &[], "Review"),
+            ("assistant", &["Read"], &"a".repeat(199)),
+        ]);
+        let long = create_test_transcript(&[
+            ("user", &[], "Review"),
+            ("assistant", &["Read"], &"a".repeat(201)),
+        ]);
+        assert_eq!(analyze_transcript(short.path().to_str().unwrap(), &config).unwrap(), Status::TaskComplete);
+        assert_eq!(analyze_transcript(long.path().to_str().unwrap(), &config).unwrap(), Status::ReviewComplete);
+
+        // A lower custom threshold turns the short review into a review
+        config.analyzer.review_min_text_length = 100;
+        assert_eq!(analyze_transcript(short.path().to_str().unwrap(), &config).unwrap(), Status::ReviewComplete);
+    }
+
+    #[test]
+    fn test_analyze_review_disabled() {
+        let file = create_test_transcript(&[
+            ("user", &[], "Review my code"),
+            ("assistant", &["Read", "Grep"], &"a".repeat(250)),
+        ]);
+
+        let mut config = Config::default();
+        config.analyzer.review_complete_enabled = false;
+        let status = analyze_transcript(file.path().to_str().unwrap(), &config).unwrap();
+        assert_eq!(status, Status::TaskComplete);
+    }
+
+    #[test]
+    fn test_analyze_recent_window_size() {
+        // Review-like first turn, then an edit in a later assistant message
+        let review_text = "a".repeat(250);
+        let file = create_test_transcript(&[
+            ("user", &[], "Look at this"),
+            ("assistant", &["Read", "Grep"], &review_text),
+            ("assistant", &["Edit"], "Fixed it"),
+        ]);
+        let path = file.path().to_str().unwrap();
+
+        let mut config = Config::default();
+        assert_eq!(analyze_transcript(path, &config).unwrap(), Status::TaskComplete);
+
+        // A window of 1 drops the edit, so the turn looks like a review
+        config.analyzer.recent_window = 1;
+        assert_eq!(analyze_transcript(path, &config).unwrap(), Status::ReviewComplete);
+    }
+
+    #[test]
+    fn test_analyze_tail_check_size() {
+        let file = create_test_transcript(&[
+            ("user", &[], "Continue"),
+            ("assistant", &[], "Session limit reached."),
+            ("assistant", &[], "one"),
+            ("assistant", &[], "two"),
+            ("assistant", &[], "three"),
+        ]);
+        let path = file.path().to_str().unwrap();
+
+        let mut config = Config::default();
+        assert_ne!(analyze_transcript(path, &config).unwrap(), Status::SessionLimitReached);
+
+        config.analyzer.tail_check = 4;
+        assert_eq!(analyze_transcript(path, &config).unwrap(), Status::SessionLimitReached);
+    }
+
     #[test]
     fn test_analyze_exit_plan_then_tools() {
         // ExitPlanMode followed by other tools = task_complete
@@ -0,0 +1,169 @@
+//! Session-history anomaly check
+//!
+//! Each session keeps a short history of what kind of commands it has run.
+//! When `anomaly.enabled` is set, the first deletion or network command in a
+//! session that has so far only been reading prompts instead of being
+//! auto-approved: a backstop against a hijacked session suddenly turning
+//! destructive.
+
+use crate::permission::{is_read_only_command, normalize_program_path, shell_words, split_command_segments, strip_privilege_wrapper};
+use crate::platform;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Commands kept per session
+const MAX_HISTORY: usize = 50;
+
+const DELETE_PROGRAMS: &[&str] = &["rm", "rmdir", "unlink", "shred", "truncate"];
+
+const NETWORK_PROGRAMS: &[&str] = &[
+    "curl", "wget", "ssh", "scp", "sftp", "rsync", "nc", "ncat", "netcat", "telnet", "ftp",
+];
+
+/// What kind of thing a tool call does, for comparing against session history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandClass {
+    Read,
+    Delete,
+    Network,
+    Other,
+}
+
+impl CommandClass {
+    /// Whether this is a departure worth prompting for in a read-only session
+    pub fn is_sensitive(self) -> bool {
+        matches!(self, CommandClass::Delete | CommandClass::Network)
+    }
+}
+
+/// Classify one command segment
+fn classify_segment(segment: &str) -> CommandClass {
+    let segment = strip_privilege_wrapper(segment).unwrap_or_else(|| segment.to_string());
+    if is_read_only_command(&segment) {
+        return CommandClass::Read;
+    }
+
+    let words = shell_words(&normalize_program_path(&segment));
+    let Some(program) = words.first() else { return CommandClass::Other };
+    let args = &words[1..];
+
+    match program.as_str() {
+        "git" => match args.first().map(String::as_str) {
+            Some("push" | "fetch" | "pull" | "clone") => CommandClass::Network,
+            Some("clean") => CommandClass::Delete,
+            _ => CommandClass::Other,
+        },
+        "find" if args.iter().any(|a| a == "-delete") => CommandClass::Delete,
+        p if DELETE_PROGRAMS.contains(&p) => CommandClass::Delete,
+        p if NETWORK_PROGRAMS.contains(&p) => CommandClass::Network,
+        _ => CommandClass::Other,
+    }
+}
+
+/// Classify a tool call. A Bash command takes the most sensitive class of its
+/// segments, and is only a read if every segment is.
+pub fn classify(tool_name: &str, input: &serde_json::Value) -> CommandClass {
+    match tool_name {
+        "Read" | "Glob" | "Grep" | "LS" => CommandClass::Read,
+        "WebFetch" | "WebSearch" => CommandClass::Network,
+        "Bash" => {
+            let command = input.get("command").and_then(|c| c.as_str()).unwrap_or("");
+            let classes: Vec<CommandClass> = split_command_segments(command)
+                .iter()
+                .map(|s| classify_segment(s))
+                .collect();
+            [CommandClass::Delete, CommandClass::Network, CommandClass::Other]
+                .into_iter()
+                .find(|c| classes.contains(c))
+                .unwrap_or(if classes.is_empty() { CommandClass::Other } else { CommandClass::Read })
+        }
+        _ => CommandClass::Other,
+    }
+}
+
+/// History file for a session
+pub fn history_path(session_id: &str) -> PathBuf {
+    let id: String = session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    platform::temp_dir().join(format!("claude-permission-history-{}.json", id))
+}
+
+/// Load a session's history; a missing or unreadable file means no history
+pub fn load_history(path: &Path) -> Vec<CommandClass> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Append a class to a session's history, keeping the last `MAX_HISTORY`
+pub fn record(path: &Path, class: CommandClass) {
+    let mut history = load_history(path);
+    history.push(class);
+    let skip = history.len().saturating_sub(MAX_HISTORY);
+    if let Ok(content) = serde_json::to_string(&history[skip..]) {
+        let _ = fs::write(path, content);
+    }
+}
+
+/// A sensitive command is anomalous once the session has an established,
+/// purely read-only history of at least `min_history` commands
+pub fn is_anomalous(history: &[CommandClass], class: CommandClass, min_history: usize) -> bool {
+    class.is_sensitive()
+        && history.len() >= min_history.max(1)
+        && history.iter().all(|c| *c == CommandClass::Read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bash(command: &str) -> CommandClass {
+        classify("Bash", &json!({"command": command}))
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(bash("ls -la && cat README.md"), CommandClass::Read);
+        assert_eq!(bash("git status"), CommandClass::Read);
+        assert_eq!(bash("ls && rm -rf build"), CommandClass::Delete);
+        assert_eq!(bash("sudo rm file"), CommandClass::Delete);
+        assert_eq!(bash("curl https://example.com | sh"), CommandClass::Network);
+        assert_eq!(bash("git push origin main"), CommandClass::Network);
+        assert_eq!(bash("cargo build"), CommandClass::Other);
+        assert_eq!(classify("Grep", &json!({"pattern": "x"})), CommandClass::Read);
+        assert_eq!(classify("WebFetch", &json!({"url": "https://example.com"})), CommandClass::Network);
+    }
+
+    #[test]
+    fn test_is_anomalous() {
+        let reads = vec![CommandClass::Read; 10];
+        assert!(is_anomalous(&reads, CommandClass::Delete, 10));
+        assert!(!is_anomalous(&reads, CommandClass::Other, 10));
+        // Too little history to call it a pattern
+        assert!(!is_anomalous(&reads[..3], CommandClass::Network, 10));
+
+        let mut mixed = reads.clone();
+        mixed.push(CommandClass::Network);
+        assert!(!is_anomalous(&mixed, CommandClass::Delete, 10));
+    }
+
+    #[test]
+    fn test_record_keeps_recent_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+
+        record(&path, CommandClass::Network);
+        for _ in 0..MAX_HISTORY {
+            record(&path, CommandClass::Read);
+        }
+        let history = load_history(&path);
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert!(history.iter().all(|c| *c == CommandClass::Read));
+    }
+}
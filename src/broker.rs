@@ -0,0 +1,242 @@
+//! Approval broker (`ambiguous.approval_broker_dir`)
+//!
+//! Hands requests that would prompt to an external approver (a custom UI, a
+//! Slack bot) through files, and waits briefly for its verdict:
+//!
+//! 1. The hook writes `<dir>/requests/<id>.json`:
+//!    `{"id", "session_id", "tool_name", "tool_input", "cwd", "created"}`
+//! 2. The approver writes `<dir>/verdicts/<id>.json`:
+//!    `{"decision": "allow" | "deny" | "prompt", "reason": "..."}` (reason optional)
+//! 3. The hook reads the verdict and removes both files.
+//!
+//! The hook only posts requests while an approver is running: it must keep
+//! `<dir>/approver.heartbeat` fresh (touch it at least every
+//! `HEARTBEAT_MAX_AGE` seconds) or hold `<dir>/approver.lock` while it runs.
+//! Otherwise the user is prompted right away instead of after the timeout.
+//!
+//! Whoever can write verdicts can approve anything, so the directory must not
+//! be writable by the agent; the hook treats it as a protected path.
+//!
+//! Both sides write to a temporary name and rename, so neither reads a half
+//! written file. Ids are unique per call, so concurrent hooks don't collide.
+//! If no verdict arrives within `approval_broker_timeout_ms` the request file
+//! is removed (the approver should treat that as withdrawn) and the user is
+//! prompted as usual.
+
+use crate::config::Config;
+use crate::permission::{Decision, HookInput};
+use crate::platform;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often to look for the verdict
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How old the approver's heartbeat file may be for it to count as running
+pub const HEARTBEAT_MAX_AGE: i64 = 60;
+
+/// What the approver writes back
+#[derive(Debug, Deserialize)]
+struct Verdict {
+    decision: String,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Unique id for a request: time, process and a per-process counter
+fn request_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    format!("{}-{}-{}", nanos, std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Write `content` so readers see either nothing or the whole file
+fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, content).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Whether an approver is running: its heartbeat is fresh or it holds its lock
+fn approver_present(dir: &Path, now: i64) -> bool {
+    let heartbeat = dir.join("approver.heartbeat");
+    let fresh = platform::file_mtime(&heartbeat.to_string_lossy()).is_some_and(|mtime| now - mtime <= HEARTBEAT_MAX_AGE);
+    fresh || dir.join("approver.lock").exists()
+}
+
+/// Ask the approval broker about a request. None if no broker is configured,
+/// it doesn't answer in time, or its verdict can't be read
+pub fn ask_approval_broker(config: &Config, input: &HookInput) -> Option<Decision> {
+    let dir = &config.ambiguous.approval_broker_dir;
+    if dir.is_empty() || !approver_present(Path::new(dir), platform::current_timestamp()) {
+        return None;
+    }
+    let id = request_id();
+    let request = serde_json::json!({
+        "id": id,
+        "session_id": input.get_session_id(),
+        "tool_name": input.get_tool_name(),
+        "tool_input": input.get_tool_input(),
+        "cwd": input.get_cwd(),
+        "created": platform::current_timestamp(),
+    });
+    let timeout = Duration::from_millis(config.ambiguous.approval_broker_timeout_ms);
+    exchange(Path::new(dir), &id, &request, timeout).ok().flatten()
+}
+
+/// Post a request and wait up to `timeout` for its verdict
+fn exchange(dir: &Path, id: &str, request: &serde_json::Value, timeout: Duration) -> Result<Option<Decision>, String> {
+    let requests = dir.join("requests");
+    let verdicts = dir.join("verdicts");
+    for sub in [&requests, &verdicts] {
+        fs::create_dir_all(sub).map_err(|e| format!("Failed to create {}: {}", sub.display(), e))?;
+    }
+
+    let request_path = requests.join(format!("{}.json", id));
+    let verdict_path = verdicts.join(format!("{}.json", id));
+    write_atomic(&request_path, &request.to_string())?;
+
+    let started = Instant::now();
+    let decision = loop {
+        let verdict = fs::read_to_string(&verdict_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Verdict>(&content).ok());
+        if let Some(verdict) = verdict {
+            break verdict_decision(&verdict);
+        }
+        if started.elapsed() >= timeout {
+            break None;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let _ = fs::remove_file(&request_path);
+    let _ = fs::remove_file(&verdict_path);
+    Ok(decision)
+}
+
+/// The decision for a verdict, with the approver's reason if it gave one
+fn verdict_decision(verdict: &Verdict) -> Option<Decision> {
+    let reason = |base: &str| match verdict.reason.trim() {
+        "" => base.to_string(),
+        detail => format!("{} '{}'", base, detail),
+    };
+    match verdict.decision.trim().to_lowercase().as_str() {
+        "allow" => Some(Decision::Allow { reason: reason("approval broker allowed") }),
+        "deny" => Some(Decision::Deny { reason: reason("approval broker denied"), segment: None }),
+        "prompt" => Some(Decision::Prompt { reason: reason("approval broker requested prompt") }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answer the first request that shows up with `verdict`, like an approver would
+    fn simulated_approver(dir: &Path, verdict: serde_json::Value) -> std::thread::JoinHandle<serde_json::Value> {
+        fs::write(dir.join("approver.heartbeat"), "").unwrap();
+        let dir = dir.to_path_buf();
+        std::thread::spawn(move || loop {
+            let request = fs::read_dir(dir.join("requests"))
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .find(|path| path.extension().is_some_and(|ext| ext == "json"));
+            if let Some(path) = request {
+                let request: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+                let id = request["id"].as_str().unwrap();
+                write_atomic(&dir.join("verdicts").join(format!("{}.json", id)), &verdict.to_string()).unwrap();
+                return request;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        })
+    }
+
+    fn input() -> HookInput {
+        serde_json::from_value(serde_json::json!({
+            "session_id": "s1",
+            "tool_name": "Bash",
+            "tool_input": {"command": "make deploy"},
+            "cwd": "/work/app",
+        }))
+        .unwrap()
+    }
+
+    fn broker_config(dir: &Path, timeout_ms: u64) -> Config {
+        let mut config = crate::config::default_config();
+        config.ambiguous.approval_broker_dir = dir.to_string_lossy().to_string();
+        config.ambiguous.approval_broker_timeout_ms = timeout_ms;
+        config
+    }
+
+    #[test]
+    fn test_broker_handshake() {
+        let dir = tempfile::tempdir().unwrap();
+        let approver = simulated_approver(dir.path(), serde_json::json!({"decision": "allow", "reason": "ok'd in #deploys"}));
+
+        let decision = ask_approval_broker(&broker_config(dir.path(), 5000), &input());
+        assert_eq!(decision, Some(Decision::Allow { reason: "approval broker allowed 'ok'd in #deploys'".into() }));
+
+        let request = approver.join().unwrap();
+        assert_eq!(request["tool_name"], "Bash");
+        assert_eq!(request["tool_input"]["command"], "make deploy");
+        assert_eq!(request["session_id"], "s1");
+        // Both files are cleaned up
+        assert_eq!(fs::read_dir(dir.path().join("requests")).unwrap().count(), 0);
+        assert_eq!(fs::read_dir(dir.path().join("verdicts")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_broker_deny_and_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = broker_config(dir.path(), 5000);
+
+        simulated_approver(dir.path(), serde_json::json!({"decision": "deny"}));
+        let decision = ask_approval_broker(&config, &input());
+        assert_eq!(decision, Some(Decision::Deny { reason: "approval broker denied".into(), segment: None }));
+
+        simulated_approver(dir.path(), serde_json::json!({"decision": "PROMPT"}));
+        assert!(matches!(ask_approval_broker(&config, &input()), Some(Decision::Prompt { .. })));
+    }
+
+    #[test]
+    fn test_broker_timeout_falls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("approver.lock"), "").unwrap();
+        let started = Instant::now();
+        assert_eq!(ask_approval_broker(&broker_config(dir.path(), 100), &input()), None);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        // The unanswered request is withdrawn
+        assert_eq!(fs::read_dir(dir.path().join("requests")).unwrap().count(), 0);
+
+        // Off unless a directory is configured
+        assert_eq!(ask_approval_broker(&crate::config::default_config(), &input()), None);
+    }
+
+    #[test]
+    fn test_broker_skipped_without_approver() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = broker_config(dir.path(), 5000);
+        let started = Instant::now();
+        assert_eq!(ask_approval_broker(&config, &input()), None);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!dir.path().join("requests").exists());
+
+        // A stale heartbeat doesn't count
+        let heartbeat = dir.path().join("approver.heartbeat");
+        fs::write(&heartbeat, "").unwrap();
+        let now = platform::current_timestamp();
+        assert!(approver_present(dir.path(), now));
+        assert!(!approver_present(dir.path(), now + HEARTBEAT_MAX_AGE + 5));
+    }
+
+    #[test]
+    fn test_request_ids_are_unique() {
+        assert_ne!(request_id(), request_id());
+    }
+}
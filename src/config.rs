@@ -1,15 +1,26 @@
 //! Configuration structures and loading for permission-hook
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // ============================================================================
 // Configuration Structures
 // ============================================================================
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct Config {
+    /// Use this file as-is instead of merging it over the built-in defaults
+    #[serde(default)]
+    pub replace_defaults: bool,
+    /// Language for notification titles and deny reasons, e.g. "es" or "de-AT"
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// Handler for each hook_event_name: "permission", "notify" (task
+    /// completion), "subagent_stop", "notification" (permission prompt) or "ignore"
+    #[serde(default = "default_events")]
+    pub events: BTreeMap<String, String>,
     #[serde(default)]
     pub features: FeaturesConfig,
     #[serde(default)]
@@ -26,9 +37,17 @@ pub struct Config {
     pub notifications: NotificationsConfig,
     #[serde(default)]
     pub updates: UpdatesConfig,
+    #[serde(default)]
+    pub analyzer: AnalyzerConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub anomaly: AnomalyConfig,
+    #[serde(default)]
+    pub state: StateConfig,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct FeaturesConfig {
     #[serde(default = "default_true")]
     pub permission_checking: bool,
@@ -38,6 +57,10 @@ pub struct FeaturesConfig {
     /// This is useful for development workflows where you want minimal prompts.
     #[serde(default = "default_true")]
     pub trust_mode: bool,
+    /// Strict mode: no trust mode or LLM, and anything that would prompt is denied.
+    /// Also turned on by a `strict` marker file or `PERMISSION_HOOK_STRICT=1`.
+    #[serde(default)]
+    pub strict_mode: bool,
 }
 
 impl Default for FeaturesConfig {
@@ -46,19 +69,47 @@ impl Default for FeaturesConfig {
             permission_checking: true,
             notifications: true,
             trust_mode: true,
+            strict_mode: false,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct AmbiguousConfig {
     #[serde(default)]
     pub mode: String,
     #[serde(default)]
     pub llm: LlmConfig,
+    /// File paths (regex) whose writes always prompt, even if otherwise auto-approved
+    #[serde(default)]
+    pub prompt_paths: Vec<String>,
+    /// File extensions (`pem`, `.key`, `.env`) whose writes always prompt, like prompt_paths
+    #[serde(default)]
+    pub prompt_extensions: Vec<String>,
+    /// Bash commands (regex, per segment) that always prompt, even if otherwise
+    /// auto-approved: shell escapes common enough in real use that denying them outright gets in the way
+    #[serde(default = "default_prompt_patterns")]
+    pub prompt_patterns: Vec<String>,
+    /// Prompt instead of auto-approving for a session's first N requests
+    #[serde(default)]
+    pub warmup_prompts: u32,
+    /// Command consulted for ambiguous requests; gets the tool JSON on stdin
+    /// and prints allow/deny/prompt
+    #[serde(default)]
+    pub external_checker: String,
+    #[serde(default = "default_external_checker_timeout")]
+    pub external_checker_timeout_ms: u64,
+    /// Directory for the file-based approval broker (see `broker`); empty = off
+    #[serde(default)]
+    pub approval_broker_dir: String,
+    #[serde(default = "default_approval_broker_timeout")]
+    pub approval_broker_timeout_ms: u64,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+fn default_external_checker_timeout() -> u64 { 5000 }
+fn default_approval_broker_timeout() -> u64 { 30000 }
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct LlmConfig {
     #[serde(default)]
     pub model: String,
@@ -66,25 +117,222 @@ pub struct LlmConfig {
     pub api_key: String,
     #[serde(default)]
     pub base_url: String,
+    /// Also send the cwd, git branch and the session's recent decisions (more tokens)
+    #[serde(default)]
+    pub include_context: bool,
+    /// Verdict when the LLM can't be reached or answers garbage: "prompt", "deny" or "allow"
+    #[serde(default = "default_llm_on_error")]
+    pub on_error: String,
+    /// Give up on the LLM request after this long and fall through (`on_error`)
+    #[serde(default = "default_llm_timeout")]
+    pub timeout_seconds: u64,
+    /// Reuse SAFE/DANGEROUS verdicts for the same tool and input for this
+    /// long, instead of asking again (0 disables)
+    #[serde(default)]
+    pub cache_ttl_seconds: u64,
+    /// Also ask the LLM about requests the patterns auto-approved; DANGEROUS
+    /// denies them, an unclear answer prompts
+    #[serde(default)]
+    pub verify_approvals: bool,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+fn default_llm_on_error() -> String { "prompt".into() }
+fn default_llm_timeout() -> u64 { 8 }
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct AutoApproveConfig {
     #[serde(default)]
     pub tools: Vec<String>,
     #[serde(default)]
     pub bash_patterns: Vec<String>,
+    /// Approve read-only commands (cat, rg, find without -delete/-exec, ...)
+    /// whose path arguments all stay inside the project directory
+    #[serde(default = "default_true")]
+    pub read_only_in_project: bool,
+    /// Never auto-approve Bash commands with `run_in_background` set (their
+    /// output isn't visible right away); they prompt unless denied
+    #[serde(default)]
+    pub strict_background: bool,
+    /// Prompt for Grep/Glob whose `path` (or absolute Glob pattern) reaches
+    /// outside the project, instead of approving them with the tool
+    #[serde(default)]
+    pub confine_searches: bool,
+    /// MCP servers whose tools are all approved, except destructive ones
+    #[serde(default)]
+    pub mcp_servers: Vec<String>,
+    /// File extensions (`md`, `.txt`) that Write/Edit may change anywhere in the project
+    #[serde(default)]
+    pub writable_extensions: Vec<String>,
+    /// Approve bare `env`/`printenv`, which print every variable (secrets included)
+    #[serde(default)]
+    pub allow_env_dump: bool,
+    /// SHA-256 hashes (`hash-command`) of exact Bash commands to approve even
+    /// though they match a deny pattern
+    #[serde(default)]
+    pub approved_command_hashes: Vec<String>,
+    /// Merge the built-in default patterns with the ones listed here
+    #[serde(default = "default_true")]
+    pub use_default_patterns: bool,
+    /// Built-in default patterns to leave out when merging
+    #[serde(default)]
+    pub disabled_default_patterns: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+impl Default for AutoApproveConfig {
+    fn default() -> Self {
+        Self {
+            tools: Vec::new(),
+            bash_patterns: Vec::new(),
+            read_only_in_project: true,
+            strict_background: false,
+            confine_searches: false,
+            mcp_servers: Vec::new(),
+            writable_extensions: Vec::new(),
+            allow_env_dump: false,
+            approved_command_hashes: Vec::new(),
+            use_default_patterns: true,
+            disabled_default_patterns: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct AutoDenyConfig {
+    /// Tools to always deny; a trailing `*` matches a prefix (`mcp__prod_db__*`)
+    #[serde(default)]
+    pub tools: Vec<String>,
     #[serde(default)]
     pub bash_patterns: Vec<String>,
     #[serde(default)]
     pub protected_paths: Vec<String>,
+    /// Protected paths as globs (`/etc/**`, `C:\Windows\**`), matched after
+    /// resolving `..` and symlinks
+    #[serde(default)]
+    pub protected_paths_glob: Vec<String>,
+    /// Paths relative to the home directory, e.g. ".ssh" protects ~/.ssh/**
+    #[serde(default = "default_protected_home_paths")]
+    pub protected_home_paths: Vec<String>,
+    /// protected_home_paths expanded into path regexes at load time
+    #[serde(skip)]
+    pub expanded_home_paths: Vec<String>,
+    /// Hosts WebFetch may not reach (cloud metadata, localhost); subdomains included
+    #[serde(default = "default_blocked_fetch_hosts")]
+    pub blocked_fetch_hosts: Vec<String>,
+    /// Commands that spawn a shell through an ordinary tool (GTFOBins), denied
+    /// and never auto-approved
+    #[serde(default = "default_shell_escape_patterns")]
+    pub shell_escape_patterns: Vec<String>,
+    /// Deny curl/wget/WebFetch to loopback, link-local and private (RFC 1918) addresses
+    #[serde(default)]
+    pub block_private_network: bool,
+    /// Deny commands that decode something (xxd -r, base64 -d, printf '\x..')
+    /// and run the result, e.g. `echo 726d... | xxd -r -p | sh`. Heuristic
+    #[serde(default)]
+    pub detect_obfuscation: bool,
+    /// What to do with `git reset --hard`: "deny" (default), "prompt", or
+    /// "allow_if_clean" (approve when there are no uncommitted changes to lose,
+    /// prompt otherwise)
+    #[serde(default = "default_git_reset_hard")]
+    pub git_reset_hard: String,
+    /// Friendly explanation per deny pattern (keyed by the pattern as written),
+    /// shown instead of the matched segment in deny messages and `--explain`
+    #[serde(default)]
+    pub deny_reasons: BTreeMap<String, String>,
+    /// Command run on every deny (SIEM logging, paging), with the decision in
+    /// PERMISSION_HOOK_TOOL/_REASON/_DETAILS environment variables
+    #[serde(default)]
+    pub on_deny_command: String,
+    /// Prompt for Bash commands nesting `$(...)`/`<(...)` deeper than this (0 = no limit)
+    #[serde(default = "default_max_nesting_depth")]
+    pub max_nesting_depth: usize,
+    /// Merge the built-in default patterns with the ones listed here
+    #[serde(default = "default_true")]
+    pub use_default_patterns: bool,
+    /// Built-in default patterns to leave out when merging
+    #[serde(default)]
+    pub disabled_default_patterns: Vec<String>,
+}
+
+impl AutoDenyConfig {
+    /// Expand protected_home_paths into regexes matching the path (and anything
+    /// under it) both as an absolute path under `home` and in `~/` form
+    pub fn expand_home_paths(&mut self, home: Option<&Path>) {
+        let case = if cfg!(windows) { "(?i)" } else { "" };
+        let mut roots = vec!["~".to_string()];
+        if let Some(home) = home {
+            roots.push(home.to_string_lossy().trim_end_matches(['/', '\\']).to_string());
+        }
+
+        self.expanded_home_paths = self
+            .protected_home_paths
+            .iter()
+            .map(|entry| entry.trim_matches(['/', '\\']))
+            .filter(|entry| !entry.is_empty())
+            .flat_map(|entry| {
+                let rel = entry
+                    .split(['/', '\\'])
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(r"[/\\]");
+                roots
+                    .iter()
+                    .map(move |root| format!(r"{}^{}[/\\]{}([/\\]|$)", case, regex::escape(root), rel))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+}
+
+fn default_max_nesting_depth() -> usize { 5 }
+
+fn default_git_reset_hard() -> String { "deny".into() }
+
+fn default_shell_escape_patterns() -> Vec<String> {
+    vec![
+        r"\b(vi|vim|nvim|rvim|view|ex)\b.*\s-c\s*['\x22]?:?\s*(!|sh(ell)?\b)".into(),
+        r"\btar\b.*--(checkpoint-action=exec|to-command)".into(),
+        r"\bzip\b.*\s-TT\s".into(),
+        r"\brsync\b.*\s-e\s*['\x22]?(\S*/)?(ba|da|z)?sh\b".into(),
+    ]
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+fn default_prompt_patterns() -> Vec<String> {
+    vec![
+        r"\bfind\b.*\s-(exec|execdir|ok|okdir)\s+(\S*/)?(ba|da|z|k|c|tc|fi)?sh(\s|$|\\?;)".into(),
+        r"\b[gmn]?awk\b.*\bsystem\s*\(".into(),
+    ]
+}
+
+fn default_blocked_fetch_hosts() -> Vec<String> {
+    vec![
+        "localhost".into(),
+        "127.0.0.0/8".into(),
+        "0.0.0.0/8".into(),
+        "10.0.0.0/8".into(),
+        "172.16.0.0/12".into(),
+        "192.168.0.0/16".into(),
+        "169.254.0.0/16".into(),
+        "::1".into(),
+        "::".into(),
+        "fc00::/7".into(),
+        "fe80::/10".into(),
+        "metadata.google.internal".into(),
+        "100.100.100.200".into(),
+    ]
+}
+
+fn default_protected_home_paths() -> Vec<String> {
+    vec![
+        ".ssh".into(),
+        ".aws".into(),
+        ".gnupg".into(),
+        ".kube".into(),
+        ".docker/config.json".into(),
+        ".netrc".into(),
+    ]
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct InlineScriptsConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -96,27 +344,68 @@ pub struct InlineScriptsConfig {
     pub dangerous_powershell_patterns: Vec<String>,
     #[serde(default)]
     pub dangerous_cmd_patterns: Vec<String>,
+    /// Read and scan script files a command runs (`./deploy.sh`, `powershell -File x.ps1`)
+    #[serde(default)]
+    pub scan_script_files: bool,
+    /// Merge the built-in default patterns with the ones listed here
+    #[serde(default = "default_true")]
+    pub use_default_patterns: bool,
+    /// Built-in default patterns to leave out when merging
+    #[serde(default)]
+    pub disabled_default_patterns: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct LoggingConfig {
     #[serde(default = "default_true")]
     pub enabled: bool,
     #[serde(default)]
     pub verbose: bool,
+    /// What goes to stderr: "silent", "decisions", "warn", or "all"
+    #[serde(default = "default_stderr_level")]
+    pub stderr_level: String,
+    /// Append a `PH-RESULT decision=... code=... tool=...` line to stderr for scripts
+    #[serde(default)]
+    pub emit_result_trailer: bool,
+    /// Longest reason kept in decisions.log (0 = no limit)
+    #[serde(default = "default_max_reason_len")]
+    pub max_reason_len: usize,
+    /// Longest details (command, path) kept in decisions.log (0 = no limit)
+    #[serde(default = "default_max_detail_len")]
+    pub max_detail_len: usize,
+    /// Also log each request's full tool_input (secrets redacted) to decision-inputs.jsonl
+    #[serde(default)]
+    pub log_full_input: bool,
+    /// Write decisions to a per-project log under `projects/`, keyed by the request's cwd
+    #[serde(default)]
+    pub per_project: bool,
 }
 
 impl Default for LoggingConfig {
     fn default() -> Self {
-        Self { enabled: true, verbose: false }
+        Self {
+            enabled: true,
+            verbose: false,
+            stderr_level: default_stderr_level(),
+            emit_result_trailer: false,
+            max_reason_len: default_max_reason_len(),
+            max_detail_len: default_max_detail_len(),
+            log_full_input: false,
+            per_project: false,
+        }
     }
 }
 
+fn default_max_reason_len() -> usize { 150 }
+fn default_max_detail_len() -> usize { 100 }
+
+fn default_stderr_level() -> String { "all".to_string() }
+
 // ============================================================================
 // Notifications Configuration (Phase 1 prep for Phase 2)
 // ============================================================================
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct NotificationsConfig {
     #[serde(default)]
     pub desktop: DesktopNotificationsConfig,
@@ -130,9 +419,28 @@ pub struct NotificationsConfig {
     pub notify_on_subagent_stop: bool,
     #[serde(default = "default_true")]
     pub notify_on_text_response: bool,
+    /// Questions arriving within this many seconds of a question notification are
+    /// coalesced into one "N questions waiting" notification (0 disables)
+    #[serde(default = "default_question_group_window")]
+    pub question_group_window_seconds: i64,
+    /// Skip desktop notifications while the terminal has focus (best-effort;
+    /// always notifies where focus can't be detected)
+    #[serde(default)]
+    pub only_when_unfocused: bool,
+    /// Show the matching part of a denied command in the alert instead of its start
+    #[serde(default = "default_true")]
+    pub alert_show_segment: bool,
+    /// Bash command patterns that trigger a notification whenever they run,
+    /// whatever the permission decision
+    #[serde(default)]
+    pub watch_patterns: Vec<String>,
+    /// Send a low-priority "still working" notification when a busy session has
+    /// notified nothing for this many seconds (0 disables)
+    #[serde(default)]
+    pub heartbeat_seconds: i64,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct DesktopNotificationsConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -140,9 +448,12 @@ pub struct DesktopNotificationsConfig {
     pub sound: bool,
     #[serde(default = "default_volume")]
     pub volume: f32,
+    /// Put session name and summary on one line (`name — summary`)
+    #[serde(default)]
+    pub single_line: bool,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct WebhookConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -156,21 +467,199 @@ pub struct WebhookConfig {
     pub retry_enabled: bool,
     #[serde(default = "default_retry_attempts")]
     pub retry_max_attempts: u32,
+    /// Truncate summaries to this many bytes (0 = the preset's platform limit)
+    #[serde(default)]
+    pub max_summary_len: usize,
 }
 
 fn default_true() -> bool { true }
+fn default_locale() -> String { "en".to_string() }
+
+fn default_events() -> BTreeMap<String, String> {
+    [
+        ("PreToolUse", "permission"),
+        ("Stop", "notify"),
+        ("SubagentStop", "subagent_stop"),
+        ("Notification", "notification"),
+    ]
+    .into_iter()
+    .map(|(event, handler)| (event.to_string(), handler.to_string()))
+    .collect()
+}
+
+impl Config {
+    /// Handler for a hook event: the `events` mapping, then the built-in one.
+    /// Unknown events get the permission check, as before events were configurable.
+    pub fn event_handler(&self, event: &str) -> String {
+        self.events
+            .get(event)
+            .cloned()
+            .or_else(|| default_events().remove(event))
+            .unwrap_or_else(|| "permission".to_string())
+    }
+}
 fn default_cooldown() -> i64 { 12 }
+fn default_question_group_window() -> i64 { 30 }
 fn default_volume() -> f32 { 1.0 }
 fn default_webhook_preset() -> String { "custom".to_string() }
 fn default_retry_attempts() -> u32 { 3 }
 fn default_check_interval_hours() -> u64 { 24 }
 fn default_github_repo() -> String { "tantk/permission-hook".to_string() }
 
+// ============================================================================
+// Analyzer Configuration
+// ============================================================================
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct AnalyzerConfig {
+    /// Max assistant messages after the last user message to analyze
+    #[serde(default = "default_recent_window")]
+    pub recent_window: usize,
+    /// How many of the latest messages to scan for session-limit/API errors
+    #[serde(default = "default_tail_check")]
+    pub tail_check: usize,
+    /// A read-only turn counts as a review only when its text is longer than this
+    #[serde(default = "default_review_min_text_length")]
+    pub review_min_text_length: usize,
+    /// Detect ReviewComplete at all (when false, reviews are reported as TaskComplete)
+    #[serde(default = "default_true")]
+    pub review_complete_enabled: bool,
+    /// Extra tools (e.g. MCP tools) that make changes, read the project, or
+    /// neither. Added to the built-in lists; a built-in tool listed in another
+    /// category moves there.
+    #[serde(default)]
+    pub active_tools: Vec<String>,
+    #[serde(default)]
+    pub read_like_tools: Vec<String>,
+    #[serde(default)]
+    pub passive_tools: Vec<String>,
+    /// Report a Question when the last assistant message ends with `?` and
+    /// changed nothing, even without AskUserQuestion (may misfire on rhetorical questions)
+    #[serde(default)]
+    pub detect_text_questions: bool,
+}
+
+impl AnalyzerConfig {
+    /// Recent window, at least 1
+    pub fn recent_window(&self) -> usize {
+        self.recent_window.max(1)
+    }
+
+    /// Tail check count, at least 1 and no more than the recent window
+    pub fn tail_check(&self) -> usize {
+        self.tail_check.clamp(1, self.recent_window())
+    }
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            recent_window: default_recent_window(),
+            tail_check: default_tail_check(),
+            review_min_text_length: default_review_min_text_length(),
+            review_complete_enabled: true,
+            active_tools: Vec::new(),
+            read_like_tools: Vec::new(),
+            passive_tools: Vec::new(),
+            detect_text_questions: false,
+        }
+    }
+}
+
+fn default_recent_window() -> usize { 15 }
+fn default_tail_check() -> usize { 3 }
+fn default_review_min_text_length() -> usize { 200 }
+
+// ============================================================================
+// Output Configuration
+// ============================================================================
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OutputConfig {
+    /// How to prompt: "passthrough" (exit 0, no output) or "ask_json"
+    /// (emit a response with permissionDecision "ask")
+    #[serde(default = "default_prompt_mode")]
+    pub prompt_mode: String,
+    /// Whether JSON responses hide the hook's reason from the transcript
+    #[serde(default)]
+    pub suppress_output: SuppressOutputConfig,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            prompt_mode: default_prompt_mode(),
+            suppress_output: SuppressOutputConfig::default(),
+        }
+    }
+}
+
+/// `suppressOutput` flag per decision type
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SuppressOutputConfig {
+    #[serde(default = "default_true")]
+    pub allow: bool,
+    #[serde(default = "default_true")]
+    pub deny: bool,
+    #[serde(default = "default_true")]
+    pub ask: bool,
+}
+
+impl Default for SuppressOutputConfig {
+    fn default() -> Self {
+        Self { allow: true, deny: true, ask: true }
+    }
+}
+
+fn default_prompt_mode() -> String { "passthrough".to_string() }
+
+// ============================================================================
+// Anomaly Configuration
+// ============================================================================
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct AnomalyConfig {
+    /// Prompt for the first deletion/network command in a read-only session
+    #[serde(default)]
+    pub enabled: bool,
+    /// Read-only commands a session needs before its pattern counts as established
+    #[serde(default = "default_anomaly_min_history")]
+    pub min_history: usize,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self { enabled: false, min_history: default_anomaly_min_history() }
+    }
+}
+
+fn default_anomaly_min_history() -> usize { 10 }
+
+// ============================================================================
+// Session State Configuration
+// ============================================================================
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct StateConfig {
+    /// Keep at most this many session-state files, newest first (0 = no cap,
+    /// the default: a cap also deletes the state of sessions still running)
+    #[serde(default = "default_max_session_files")]
+    pub max_session_files: usize,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self { max_session_files: default_max_session_files() }
+    }
+}
+
+fn default_max_session_files() -> usize { 0 }
+
 // ============================================================================
 // Updates Configuration
 // ============================================================================
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct UpdatesConfig {
     #[serde(default)]
     pub check_enabled: bool,
@@ -208,20 +697,94 @@ pub fn get_config_path() -> PathBuf {
     get_config_dir().join("config.json")
 }
 
+/// Candidate config files in precedence order: JSON comes first for backward
+/// compatibility, then YAML (`.yaml` before `.yml`), then TOML. If several
+/// exist, the first one found wins and the others are ignored (with a warning).
+pub fn get_config_paths() -> Vec<PathBuf> {
+    let dir = get_config_dir();
+    vec![
+        dir.join("config.json"),
+        dir.join("config.yaml"),
+        dir.join("config.yml"),
+        dir.join("config.toml"),
+    ]
+}
+
+/// The config file that will be loaded, if any exists
+pub fn find_config_path() -> Option<PathBuf> {
+    get_config_paths().into_iter().find(|p| p.exists())
+}
+
+/// Warning for config files that exist but are ignored because one earlier in
+/// `found` (in precedence order) is loaded instead
+pub fn shadowed_config_warning(found: &[PathBuf]) -> Option<String> {
+    let (loaded, ignored) = found.split_first()?;
+    if ignored.is_empty() {
+        return None;
+    }
+    let ignored: Vec<String> = ignored.iter().map(|p| p.display().to_string()).collect();
+    Some(format!(
+        "Several config files found; using {} and ignoring {}",
+        loaded.display(),
+        ignored.join(", ")
+    ))
+}
+
 pub fn get_log_path() -> PathBuf {
     get_config_dir().join("decisions.log")
 }
 
+/// Decisions log for one project directory (`logging.per_project`), named
+/// after the directory and a hash of its canonical path, so different paths
+/// never share a log: `/home/dev/app` logs to `projects/app-<hash>.log`.
+/// None for an empty cwd or a filesystem root.
+pub fn get_project_log_path(cwd: &str) -> Option<PathBuf> {
+    let cwd = cwd.trim();
+    if cwd.is_empty() {
+        return None;
+    }
+    let path = fs::canonicalize(cwd).unwrap_or_else(|_| PathBuf::from(cwd));
+    let name: String = path
+        .file_name()?
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '-' })
+        .collect();
+    let hash = crate::permission::command_hash(&path.to_string_lossy());
+    Some(get_config_dir().join("projects").join(format!("{}-{}.log", name.trim_matches('-'), &hash[..16])))
+}
+
+/// Marker file that turns on strict mode while it exists
+pub fn get_strict_marker_path() -> PathBuf {
+    get_config_dir().join("strict")
+}
+
+pub fn get_inputs_log_path() -> PathBuf {
+    get_config_dir().join("decision-inputs.jsonl")
+}
+
 pub fn get_prompts_path() -> PathBuf {
     get_config_dir().join("recent_prompts.log")
 }
 
+pub fn get_llm_cache_path() -> PathBuf {
+    get_config_dir().join("llm_cache.json")
+}
+
+/// Project config files the user reviewed (`trust-project`), by path and hash
+pub fn get_trusted_projects_path() -> PathBuf {
+    get_config_dir().join("trusted_projects.json")
+}
+
 // ============================================================================
 // Default Configuration
 // ============================================================================
 
 pub fn default_config() -> Config {
-    Config {
+    let mut config = Config {
+        replace_defaults: false,
+        locale: default_locale(),
+        events: default_events(),
         features: FeaturesConfig::default(),
         auto_approve: AutoApproveConfig {
             tools: vec![
@@ -239,7 +802,6 @@ pub fn default_config() -> Config {
                 r"^head\s".into(),
                 r"^tail\s".into(),
                 r"^grep\s".into(),
-                r"^find\s".into(),
                 r"^wc\s".into(),
                 r"^stat\s".into(),
                 r"^tree(\s|$)".into(),
@@ -258,8 +820,18 @@ pub fn default_config() -> Config {
                 r"^gh\s+auth\s+status".into(),
                 r"^(whoami|hostname|date|uname|env)$".into(),
             ],
+            read_only_in_project: true,
+            strict_background: false,
+            confine_searches: false,
+            mcp_servers: Vec::new(),
+            writable_extensions: Vec::new(),
+            allow_env_dump: false,
+            approved_command_hashes: Vec::new(),
+            use_default_patterns: true,
+            disabled_default_patterns: Vec::new(),
         },
         auto_deny: AutoDenyConfig {
+            tools: Vec::new(),
             bash_patterns: vec![
                 r"rm\s+(-rf?|--recursive)?\s*[/~]".into(),
                 r"rm\s+-rf?\s+\*".into(),
@@ -267,13 +839,21 @@ pub fn default_config() -> Config {
                 r"git\s+reset\s+--hard".into(),
                 r"curl.*\|\s*(ba)?sh".into(),
                 r"wget.*\|\s*(ba)?sh".into(),
-                r"sudo\s+rm".into(),
+                r"(sudo|doas|pkexec|run0)\s+rm".into(),
                 r"npm\s+publish".into(),
                 r"yarn\s+publish".into(),
                 r"mkfs\.".into(),
                 r"dd\s+.*of=/dev".into(),
                 r">\s*/etc/".into(),
                 r"chmod\s+(-R\s+)?777\s+/".into(),
+                // git settings that run arbitrary commands: fsmonitor, ssh command,
+                // hooks outside the project and shell (`!`) aliases. A hooks dir in
+                // the repository (`.githooks`) and the pager are common setup, and
+                // prompt like any other git config change.
+                r"(?i)git\s+config\s+(--\S+\s+)*core\.(fsmonitor|sshCommand)\s+\S".into(),
+                r"(?i)git\s+config\s+(--\S+\s+)*core\.hooksPath\s+['\x22]?([/~$\\]|[a-z]:|\.\.)".into(),
+                r"(?i)git\s+config\s+(--\S+\s+)*alias\.\S+\s+['\x22]?!".into(),
+                r"(?i)git\s+(.*\s)?-c\s+['\x22]?(core\.(fsmonitor|sshCommand)=|core\.hooksPath=['\x22]?([/~$\\]|[a-z]:|\.\.)|alias\.\S+=!)".into(),
             ],
             protected_paths: vec![
                 r"^/etc/".into(),
@@ -283,6 +863,26 @@ pub fn default_config() -> Config {
                 r"(?i)^C:\\Windows".into(),
                 r"(?i)^C:\\Program Files".into(),
             ],
+            protected_paths_glob: vec![
+                "/etc/**".into(),
+                "/usr/**".into(),
+                "/bin/**".into(),
+                "/sbin/**".into(),
+                r"C:\Windows\**".into(),
+                r"C:\Program Files\**".into(),
+            ],
+            protected_home_paths: default_protected_home_paths(),
+            expanded_home_paths: Vec::new(),
+            blocked_fetch_hosts: default_blocked_fetch_hosts(),
+            shell_escape_patterns: default_shell_escape_patterns(),
+            block_private_network: false,
+            detect_obfuscation: false,
+            git_reset_hard: default_git_reset_hard(),
+            deny_reasons: BTreeMap::new(),
+            max_nesting_depth: default_max_nesting_depth(),
+            on_deny_command: String::new(),
+            use_default_patterns: true,
+            disabled_default_patterns: Vec::new(),
         },
         inline_scripts: InlineScriptsConfig {
             enabled: true,
@@ -293,6 +893,10 @@ pub fn default_config() -> Config {
                 r"os\.system".into(),
                 r"shutil\.rmtree".into(),
                 r"subprocess".into(),
+                // Environment sent over the network (exfiltration); a plain
+                // env read on its own is fine
+                r"(?s)(os\.environ|os\.getenv).*(requests|urllib|http\.client|httpx|socket)".into(),
+                r"(?s)(requests|urllib|http\.client|httpx|socket).*(os\.environ|os\.getenv)".into(),
             ],
             dangerous_node_patterns: vec![
                 r"child_process".into(),
@@ -300,6 +904,8 @@ pub fn default_config() -> Config {
                 r"fs\.rmdir".into(),
                 r"fs\.rm\(".into(),
                 r"rimraf".into(),
+                r"(?s)process\.env.*(fetch\(|https?\.request|https?\.get|axios|net\.connect|XMLHttpRequest)".into(),
+                r"(?s)(fetch\(|https?\.request|https?\.get|axios|net\.connect|XMLHttpRequest).*process\.env".into(),
             ],
             dangerous_powershell_patterns: vec![
                 r"(?i)Remove-Item".into(),
@@ -317,6 +923,14 @@ pub fn default_config() -> Config {
                 r"(?i)Disable-".into(),
                 r"(?i)Stop-Service".into(),
                 r"(?i)Uninstall-".into(),
+                // Clipboard, credential and registry secret reads, and system
+                // enumeration, which can feed exfiltration
+                r"(?i)Get-Clipboard".into(),
+                r"(?i)Get-Credential".into(),
+                r"(?i)ConvertFrom-SecureString".into(),
+                r"(?i)GetNetworkCredential|PasswordVault".into(),
+                r"(?i)\b(Get-ItemProperty|gp)\s.*(\\SAM\b|\\SECURITY\b|Winlogon|PuTTY\\Sessions)".into(),
+                r"(?i)\b(Get-WmiObject|gwmi)\b".into(),
             ],
             dangerous_cmd_patterns: vec![
                 r"(?i)\bdel\b".into(),
@@ -326,6 +940,9 @@ pub fn default_config() -> Config {
                 r"(?i)\bformat\b".into(),
                 r"(?i)\bdiskpart\b".into(),
             ],
+            scan_script_files: false,
+            use_default_patterns: true,
+            disabled_default_patterns: Vec::new(),
         },
         ambiguous: AmbiguousConfig {
             mode: "ask".into(),
@@ -333,28 +950,434 @@ pub fn default_config() -> Config {
                 model: "openai/gpt-4o-mini".into(),
                 api_key: "".into(),
                 base_url: "https://openrouter.ai/api/v1".into(),
+                include_context: false,
+                on_error: default_llm_on_error(),
+                timeout_seconds: default_llm_timeout(),
+                cache_ttl_seconds: 0,
+                verify_approvals: false,
+            },
+            prompt_paths: Vec::new(),
+            prompt_extensions: Vec::new(),
+            prompt_patterns: default_prompt_patterns(),
+            warmup_prompts: 0,
+            external_checker: String::new(),
+            external_checker_timeout_ms: default_external_checker_timeout(),
+            approval_broker_dir: String::new(),
+            approval_broker_timeout_ms: default_approval_broker_timeout(),
+        },
+        logging: LoggingConfig::default(),
+        notifications: NotificationsConfig {
+            desktop: DesktopNotificationsConfig {
+                enabled: false,
+                sound: false,
+                volume: default_volume(),
+                single_line: false,
+            },
+            webhook: WebhookConfig {
+                enabled: false,
+                url: String::new(),
+                preset: default_webhook_preset(),
+                telegram_chat_id: None,
+                retry_enabled: true,
+                retry_max_attempts: default_retry_attempts(),
+                max_summary_len: 0,
             },
+            suppress_question_after_task_complete_seconds: default_cooldown(),
+            suppress_question_after_any_notification_seconds: default_cooldown(),
+            notify_on_subagent_stop: false,
+            notify_on_text_response: true,
+            question_group_window_seconds: default_question_group_window(),
+            only_when_unfocused: false,
+            alert_show_segment: true,
+            watch_patterns: Vec::new(),
+            heartbeat_seconds: 0,
         },
-        logging: LoggingConfig { enabled: true, verbose: false },
-        notifications: NotificationsConfig::default(),
         updates: UpdatesConfig::default(),
-    }
+        analyzer: AnalyzerConfig::default(),
+        output: OutputConfig::default(),
+        anomaly: AnomalyConfig::default(),
+        state: StateConfig::default(),
+    };
+    config.auto_deny.expand_home_paths(dirs::home_dir().as_deref());
+    config
 }
 
 // ============================================================================
 // Config Loading
 // ============================================================================
 
+/// Supported config file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Detect the format from the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(ConfigFormat::Json),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "toml" => Some(ConfigFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+/// Expand `${VAR}` references from the environment, so secrets like webhook
+/// tokens can stay out of the config file. An unset variable is an error.
+pub fn expand_env_vars(value: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("Unterminated ${{...}} in '{}'", value))?;
+        let name = &after[..end];
+        let resolved = std::env::var(name)
+            .map_err(|_| format!("Environment variable {} is not set", name))?;
+        out.push_str(&resolved);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Parse config file content in the given format
+///
+/// Unless `replace_defaults` is set, the file is deep-merged over `default_config()`:
+/// settings it omits keep their built-in values and pattern lists are merged
+/// with the defaults (see `merge_default_patterns`).
+pub fn parse_config(content: &str, format: ConfigFormat) -> Result<Config, String> {
+    // Typed parse first so errors point at the offending line
+    let parsed: Config = match format {
+        ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string())?,
+        ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string())?,
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string())?,
+    };
+
+    let mut config = if parsed.replace_defaults {
+        parsed
+    } else {
+        let user: serde_json::Value = match format {
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string())?,
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string())?,
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string())?,
+        };
+        let defaults = default_config();
+        let mut merged = serde_json::to_value(without_patterns(defaults.clone()))
+            .map_err(|e| e.to_string())?;
+        merge_values(&mut merged, user);
+        let mut config: Config = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+        merge_default_patterns(&mut config, &defaults);
+        config
+    };
+
+    config.auto_deny.expand_home_paths(dirs::home_dir().as_deref());
+    Ok(config)
+}
+
+/// Recursively overlay `overlay` onto `base`; objects merge key by key,
+/// anything else (including arrays) is replaced
+fn merge_values(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        // An empty YAML document parses as null
+        (_, serde_json::Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Defaults with their tool and pattern lists emptied; those lists are merged
+/// by `merge_default_patterns` so per-category opt-outs apply
+fn without_patterns(mut config: Config) -> Config {
+    config.auto_approve.tools.clear();
+    config.auto_approve.bash_patterns.clear();
+    config.auto_deny.bash_patterns.clear();
+    config.auto_deny.protected_paths.clear();
+    config.auto_deny.protected_paths_glob.clear();
+    config.auto_deny.protected_home_paths.clear();
+    config.auto_deny.blocked_fetch_hosts.clear();
+    config.auto_deny.shell_escape_patterns.clear();
+    config.ambiguous.prompt_patterns.clear();
+    config.inline_scripts.dangerous_python_patterns.clear();
+    config.inline_scripts.dangerous_node_patterns.clear();
+    config.inline_scripts.dangerous_powershell_patterns.clear();
+    config.inline_scripts.dangerous_cmd_patterns.clear();
+    config
+}
+
+/// Merge the built-in default patterns into a loaded config, per category.
+/// Categories with `use_default_patterns: false` keep only the user's patterns.
+pub fn merge_default_patterns(config: &mut Config, defaults: &Config) {
+    let approve = &mut config.auto_approve;
+    if approve.use_default_patterns {
+        let disabled = &approve.disabled_default_patterns;
+        merge_patterns(&mut approve.tools, &defaults.auto_approve.tools, disabled);
+        merge_patterns(&mut approve.bash_patterns, &defaults.auto_approve.bash_patterns, disabled);
+    }
+
+    let deny = &mut config.auto_deny;
+    if deny.use_default_patterns {
+        let disabled = &deny.disabled_default_patterns;
+        merge_patterns(&mut deny.bash_patterns, &defaults.auto_deny.bash_patterns, disabled);
+        merge_patterns(&mut deny.protected_paths, &defaults.auto_deny.protected_paths, disabled);
+        merge_patterns(&mut deny.protected_paths_glob, &defaults.auto_deny.protected_paths_glob, disabled);
+        merge_patterns(&mut deny.protected_home_paths, &defaults.auto_deny.protected_home_paths, disabled);
+        merge_patterns(&mut deny.blocked_fetch_hosts, &defaults.auto_deny.blocked_fetch_hosts, disabled);
+        merge_patterns(&mut deny.shell_escape_patterns, &defaults.auto_deny.shell_escape_patterns, disabled);
+        // The prompting shell escapes go with the denied ones
+        merge_patterns(&mut config.ambiguous.prompt_patterns, &defaults.ambiguous.prompt_patterns, disabled);
+    }
+
+    let scripts = &mut config.inline_scripts;
+    if scripts.use_default_patterns {
+        let disabled = &scripts.disabled_default_patterns;
+        let inline_defaults = &defaults.inline_scripts;
+        merge_patterns(&mut scripts.dangerous_python_patterns, &inline_defaults.dangerous_python_patterns, disabled);
+        merge_patterns(&mut scripts.dangerous_node_patterns, &inline_defaults.dangerous_node_patterns, disabled);
+        merge_patterns(&mut scripts.dangerous_powershell_patterns, &inline_defaults.dangerous_powershell_patterns, disabled);
+        merge_patterns(&mut scripts.dangerous_cmd_patterns, &inline_defaults.dangerous_cmd_patterns, disabled);
+    }
+}
+
+/// Defaults first (minus ones the user already lists), then user additions;
+/// disabled patterns are dropped from both
+fn merge_patterns(user: &mut Vec<String>, defaults: &[String], disabled: &[String]) {
+    let mut merged: Vec<String> = defaults
+        .iter()
+        .filter(|p| !user.contains(p))
+        .cloned()
+        .collect();
+    merged.append(user);
+    merged.retain(|p| !disabled.contains(p));
+    *user = merged;
+}
+
+/// Load a config file, detecting its format by extension
+pub fn load_config_file(path: &Path) -> Result<Config, String> {
+    let format = ConfigFormat::from_path(path)
+        .ok_or_else(|| format!("Unsupported config format: {}", path.display()))?;
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_config(&content, format)
+}
+
 pub fn load_config() -> Config {
-    let config_path = get_config_path();
+    let found: Vec<PathBuf> = get_config_paths().into_iter().filter(|p| p.exists()).collect();
+    let mut config = match found.first().map(|path| (path, load_config_file(path))) {
+        Some((_, Ok(config))) => config,
+        Some((path, Err(e))) => {
+            let config = default_config();
+            crate::logging::warn(
+                &config,
+                &format!("Ignoring {} and using defaults: {}", path.display(), e),
+            );
+            config
+        }
+        None => default_config(),
+    };
+
+    let env_strict = std::env::var("PERMISSION_HOOK_STRICT").ok();
+    if strict_mode_requested(&get_strict_marker_path(), env_strict.as_deref()) {
+        config.features.strict_mode = true;
+    }
+    if let Some(warning) = shadowed_config_warning(&found) {
+        crate::logging::warn(&config, &warning);
+    }
+    config
+}
+
+/// Per-project config file, looked up from the session's cwd upwards
+pub const PROJECT_CONFIG_FILE: &str = ".claude-permission-hook.json";
+
+/// The nearest `.claude-permission-hook.json` in `cwd` or one of its parents
+pub fn find_project_config(cwd: &str) -> Option<PathBuf> {
+    if cwd.is_empty() {
+        return None;
+    }
+    Path::new(cwd)
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Settings a project config may extend. A project file comes with the
+/// repository, so it can only add deny and prompt rules: never approvals,
+/// commands, URLs, credentials or switches that loosen a check.
+const PROJECT_CONFIG_KEYS: &[(&str, &[&str])] = &[
+    (
+        "auto_deny",
+        &[
+            "tools",
+            "bash_patterns",
+            "protected_paths",
+            "protected_paths_glob",
+            "protected_home_paths",
+            "blocked_fetch_hosts",
+            "shell_escape_patterns",
+            "deny_reasons",
+        ],
+    ),
+    ("ambiguous", &["prompt_paths", "prompt_extensions", "prompt_patterns"]),
+    (
+        "inline_scripts",
+        &[
+            "dangerous_python_patterns",
+            "dangerous_node_patterns",
+            "dangerous_powershell_patterns",
+            "dangerous_cmd_patterns",
+        ],
+    ),
+];
+
+/// Check that a project config only adds rules (see `PROJECT_CONFIG_KEYS`):
+/// lists of strings, plus reasons for its own deny patterns
+pub fn check_project_overlay(overlay: &serde_json::Value) -> Result<(), String> {
+    let sections = overlay.as_object().ok_or("expected a JSON object")?;
+    for (section, settings) in sections {
+        let allowed = PROJECT_CONFIG_KEYS
+            .iter()
+            .find(|(name, _)| name == section)
+            .map(|(_, keys)| *keys)
+            .ok_or_else(|| format!("'{}' can't be set by a project config", section))?;
+        let settings = settings.as_object().ok_or_else(|| format!("'{}' must be an object", section))?;
+        for (key, value) in settings {
+            if !allowed.contains(&key.as_str()) {
+                return Err(format!("'{}.{}' can't be set by a project config (only deny and prompt rules can be added)", section, key));
+            }
+            let strings = match value {
+                serde_json::Value::Array(items) => items.iter().all(|v| v.is_string()),
+                serde_json::Value::Object(items) if key == "deny_reasons" => items.values().all(|v| v.is_string()),
+                _ => false,
+            };
+            if !strings {
+                return Err(format!("'{}.{}' must be a list of strings", section, key));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the user trusted this exact project config file (`trust-project`)
+fn is_project_trusted(trust_path: &Path, path: &Path, content: &str) -> bool {
+    let trusted: BTreeMap<String, String> = fs::read_to_string(trust_path)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default();
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).to_string_lossy().to_string();
+    trusted.get(&key).is_some_and(|hash| *hash == crate::permission::command_hash(content))
+}
+
+/// Trust the project config at `path` as it is now; any later edit has to be
+/// trusted again. Returns the file's hash.
+pub fn trust_project_config(trust_path: &Path, path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let overlay: serde_json::Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+    check_project_overlay(&overlay).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+
+    let mut trusted: BTreeMap<String, String> = fs::read_to_string(trust_path)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default();
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).to_string_lossy().to_string();
+    let hash = crate::permission::command_hash(&content);
+    trusted.insert(key, hash.clone());
+    let content = serde_json::to_string_pretty(&trusted).map_err(|e| e.to_string())?;
+    crate::platform::write_private_file(trust_path, &content)
+        .map_err(|e| format!("Failed to write {}: {}", trust_path.display(), e))?;
+    Ok(hash)
+}
+
+/// Overlay a project config on `base`: objects merge key by key, arrays are
+/// appended (skipping entries already present), anything else is replaced
+pub fn merge_config(base: &Config, overlay: serde_json::Value) -> Result<Config, String> {
+    let mut merged = serde_json::to_value(base).map_err(|e| e.to_string())?;
+    append_values(&mut merged, overlay);
+    let mut config: Config = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+    config.auto_deny.expand_home_paths(dirs::home_dir().as_deref());
+    Ok(config)
+}
 
-    if let Ok(content) = fs::read_to_string(&config_path) {
-        if let Ok(config) = serde_json::from_str(&content) {
-            return config;
+/// `merge_values`, except arrays are extended instead of replaced
+fn append_values(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => append_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (serde_json::Value::Array(base), serde_json::Value::Array(overlay)) => {
+            for value in overlay {
+                if !base.contains(&value) {
+                    base.push(value);
+                }
+            }
         }
+        (_, serde_json::Value::Null) => {}
+        (base, overlay) => *base = overlay,
     }
+}
 
-    default_config()
+/// The global config with the project's `.claude-permission-hook.json` (if
+/// any) merged on top. The file must be trusted (`trust-project`) and may only
+/// add deny and prompt rules. Strict mode requested by env or marker file stays on.
+pub fn load_project_config(base: &Config, cwd: &str) -> Result<Config, String> {
+    load_project_config_with(base, cwd, &get_trusted_projects_path())
+}
+
+fn load_project_config_with(base: &Config, cwd: &str, trust_path: &Path) -> Result<Config, String> {
+    let Some(path) = find_project_config(cwd) else {
+        return Ok(base.clone());
+    };
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !is_project_trusted(trust_path, &path, &content) {
+        return Err(format!(
+            "Ignoring {}: not trusted (review it, then run `claude-permission-hook trust-project {}`)",
+            path.display(),
+            path.parent().unwrap_or(Path::new(".")).display()
+        ));
+    }
+    let mut overlay: serde_json::Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+    check_project_overlay(&overlay).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+    // Reasons only for patterns the user hasn't explained already
+    if let Some(reasons) = overlay.pointer_mut("/auto_deny/deny_reasons").and_then(|r| r.as_object_mut()) {
+        reasons.retain(|pattern, _| !base.auto_deny.deny_reasons.contains_key(pattern));
+    }
+    let mut config = merge_config(base, overlay).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+    let env_strict = std::env::var("PERMISSION_HOOK_STRICT").ok();
+    if strict_mode_requested(&get_strict_marker_path(), env_strict.as_deref()) {
+        config.features.strict_mode = true;
+    }
+    Ok(config)
+}
+
+/// Whether strict mode is switched on outside the config: by the marker file
+/// or `PERMISSION_HOOK_STRICT` set to 1/true
+pub fn strict_mode_requested(marker: &Path, env_value: Option<&str>) -> bool {
+    let env_on = env_value.is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    env_on || marker.exists()
 }
 
 #[cfg(test)]
@@ -366,6 +1389,332 @@ mod tests {
         let config = default_config();
         assert!(config.auto_approve.tools.contains(&"Read".to_string()));
         assert!(config.logging.enabled);
+        // Capping session files would delete running sessions' state
+        assert_eq!(config.state.max_session_files, 0);
+    }
+
+    #[test]
+    fn test_strict_mode_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("strict");
+        assert!(!strict_mode_requested(&marker, None));
+        assert!(!strict_mode_requested(&marker, Some("0")));
+        assert!(strict_mode_requested(&marker, Some("1")));
+        assert!(strict_mode_requested(&marker, Some("TRUE")));
+
+        fs::write(&marker, "").unwrap();
+        assert!(strict_mode_requested(&marker, None));
+    }
+
+    #[test]
+    fn test_merge_config_appends_and_overrides() {
+        let mut base = default_config();
+        base.ambiguous.mode = "passthrough".into();
+        let existing = base.auto_approve.bash_patterns[0].clone();
+        let overlay = serde_json::json!({
+            "auto_approve": { "bash_patterns": ["^make test$", existing] },
+            "auto_deny": { "protected_paths": ["deploy/prod\\.env"] },
+            "ambiguous": { "mode": "llm" }
+        });
+        let merged = merge_config(&base, overlay).unwrap();
+
+        assert_eq!(merged.ambiguous.mode, "llm");
+        assert_eq!(merged.auto_deny.protected_paths.last().unwrap(), "deploy/prod\\.env");
+        assert!(merged.auto_deny.protected_paths.starts_with(&base.auto_deny.protected_paths));
+        let patterns = &merged.auto_approve.bash_patterns;
+        assert!(patterns.starts_with(&base.auto_approve.bash_patterns));
+        assert_eq!(patterns.last().unwrap(), "^make test$");
+        assert_eq!(patterns.len(), base.auto_approve.bash_patterns.len() + 1);
+        assert_eq!(merged.auto_deny.expanded_home_paths, base.auto_deny.expanded_home_paths);
+    }
+
+    #[test]
+    fn test_project_config_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        let base = default_config();
+
+        // No project file: the global config as-is
+        let loaded = load_project_config(&base, nested.to_str().unwrap()).unwrap();
+        assert_eq!(loaded, base);
+
+        let trust_path = dir.path().join("trusted_projects.json");
+        let project_file = dir.path().join(PROJECT_CONFIG_FILE);
+        let load = || load_project_config_with(&base, nested.to_str().unwrap(), &trust_path);
+        fs::write(&project_file, r#"{"auto_deny": {"bash_patterns": ["^make deploy"]}}"#).unwrap();
+        assert_eq!(find_project_config(nested.to_str().unwrap()), Some(project_file.clone()));
+
+        // Not applied until the user trusts it
+        assert!(load().unwrap_err().contains("not trusted"));
+        trust_project_config(&trust_path, &project_file).unwrap();
+        assert_eq!(load().unwrap().auto_deny.bash_patterns.last().unwrap(), "^make deploy");
+
+        // Editing it withdraws the trust
+        fs::write(&project_file, r#"{"auto_deny": {"bash_patterns": [".*"]}}"#).unwrap();
+        assert!(load().unwrap_err().contains("not trusted"));
+
+        fs::write(&project_file, "{not json").unwrap();
+        assert!(trust_project_config(&trust_path, &project_file).is_err());
+    }
+
+    #[test]
+    fn test_project_overlay_only_tightens() {
+        let ok = serde_json::json!({
+            "auto_deny": {"bash_patterns": ["^make deploy"], "deny_reasons": {"^make deploy": "Deploys from CI only"}},
+            "ambiguous": {"prompt_paths": ["migrations/"]},
+        });
+        assert!(check_project_overlay(&ok).is_ok());
+
+        for overlay in [
+            serde_json::json!({"auto_approve": {"bash_patterns": [".*"]}}),
+            serde_json::json!({"ambiguous": {"external_checker": "./approve-all.sh"}}),
+            serde_json::json!({"ambiguous": {"llm": {"base_url": "https://attacker.example"}}}),
+            serde_json::json!({"auto_deny": {"detect_obfuscation": false}}),
+            serde_json::json!({"auto_deny": {"block_private_network": false}}),
+            serde_json::json!({"auto_deny": {"max_nesting_depth": 99}}),
+            serde_json::json!({"auto_deny": {"git_reset_hard": "allow"}}),
+            serde_json::json!({"auto_deny": {"on_deny_command": "curl evil"}}),
+            serde_json::json!({"auto_deny": {"bash_patterns": "rm"}}),
+            serde_json::json!({"features": {"trust_mode": true}}),
+            serde_json::json!({"notifications": {"webhook": {"url": "https://attacker.example"}}}),
+            serde_json::json!(["not", "an", "object"]),
+        ] {
+            assert!(check_project_overlay(&overlay).is_err(), "{}", overlay);
+        }
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        std::env::set_var("PH_TEST_BOT_TOKEN", "123:abc");
+        assert_eq!(
+            expand_env_vars("https://api.telegram.org/bot${PH_TEST_BOT_TOKEN}/sendMessage").unwrap(),
+            "https://api.telegram.org/bot123:abc/sendMessage"
+        );
+        assert_eq!(expand_env_vars("no vars").unwrap(), "no vars");
+        assert!(expand_env_vars("${PH_TEST_UNSET_VAR_12345}").unwrap_err().contains("PH_TEST_UNSET_VAR_12345"));
+        assert!(expand_env_vars("${OPEN").is_err());
+    }
+
+    #[test]
+    fn test_expand_home_paths() {
+        let mut auto_deny = AutoDenyConfig {
+            protected_home_paths: vec![".ssh".into(), ".docker/config.json".into()],
+            ..Default::default()
+        };
+        auto_deny.expand_home_paths(Some(Path::new("/home/dev")));
+
+        let matches = |path: &str| {
+            auto_deny.expanded_home_paths.iter().any(|p| regex::Regex::new(p).unwrap().is_match(path))
+        };
+        assert!(matches("/home/dev/.ssh/authorized_keys"));
+        assert!(matches("/home/dev/.ssh"));
+        assert!(matches("~/.ssh/id_rsa"));
+        assert!(matches("/home/dev/.docker/config.json"));
+        assert!(!matches("/home/dev/.sshrc"));
+        assert!(!matches("/home/dev/project/.ssh/key"));
+        assert!(!matches("/home/dev/.docker/other.json"));
+    }
+
+    #[test]
+    fn test_user_patterns_merge_with_defaults() {
+        let config = parse_config(
+            r#"{ "auto_deny": { "bash_patterns": ["terraform\\s+destroy"] } }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        let defaults = default_config();
+
+        let deny = &config.auto_deny.bash_patterns;
+        assert_eq!(deny.last().map(String::as_str), Some(r"terraform\s+destroy"));
+        assert!(defaults.auto_deny.bash_patterns.iter().all(|p| deny.contains(p)));
+        assert_eq!(config.auto_approve.bash_patterns, defaults.auto_approve.bash_patterns);
+        assert_eq!(config.auto_deny.protected_paths, defaults.auto_deny.protected_paths);
+    }
+
+    #[test]
+    fn test_disable_default_patterns() {
+        let config = parse_config(
+            r#"{
+                "auto_approve": { "disabled_default_patterns": ["^sed\\s"] },
+                "auto_deny": { "use_default_patterns": false, "bash_patterns": ["rm\\s+-rf"] }
+            }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+
+        assert!(!config.auto_approve.bash_patterns.contains(&r"^sed\s".to_string()));
+        assert!(config.auto_approve.bash_patterns.contains(&r"^cat\s".to_string()));
+        assert_eq!(config.auto_deny.bash_patterns, vec![r"rm\s+-rf".to_string()]);
+        assert!(config.auto_deny.protected_paths.is_empty());
+        assert!(config.auto_deny.protected_paths_glob.is_empty());
+        assert!(config.auto_deny.shell_escape_patterns.is_empty());
+        assert!(config.ambiguous.prompt_patterns.is_empty());
+    }
+
+    #[test]
+    fn test_minimal_config_keeps_default_protections() {
+        let config = parse_config(
+            r#"{ "auto_deny": { "bash_patterns": ["terraform\\s+destroy"] }, "features": { "trust_mode": false } }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+
+        let denies = |cmd: &str| {
+            config.auto_deny.bash_patterns.iter().any(|p| regex::Regex::new(p).unwrap().is_match(cmd))
+        };
+        assert!(denies("rm -rf /"));
+        assert!(denies("terraform destroy"));
+
+        // Omitted settings keep their built-in values
+        let defaults = default_config();
+        assert!(!config.features.trust_mode);
+        assert!(config.features.permission_checking);
+        assert!(config.inline_scripts.enabled);
+        assert_eq!(config.ambiguous.mode, "ask");
+        assert_eq!(config.auto_approve.tools, defaults.auto_approve.tools);
+
+        // An empty file is exactly the defaults
+        assert_eq!(parse_config("{}", ConfigFormat::Json).unwrap(), defaults);
+    }
+
+    #[test]
+    fn test_replace_defaults() {
+        let config = parse_config(
+            r#"{ "replace_defaults": true, "auto_approve": { "tools": ["Read"] } }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+
+        assert_eq!(config.auto_approve.tools, vec!["Read".to_string()]);
+        assert!(config.auto_deny.bash_patterns.is_empty());
+        assert!(!config.inline_scripts.enabled);
+    }
+
+    const JSON_CONFIG: &str = r#"{
+        "features": { "trust_mode": false },
+        "auto_deny": { "bash_patterns": ["rm\\s+-rf"], "protected_paths": ["^/etc/"] },
+        "notifications": { "desktop": { "enabled": true, "volume": 0.5 } }
+    }"#;
+
+    const YAML_CONFIG: &str = r#"
+features:
+  trust_mode: false
+auto_deny:
+  # comments are the point of YAML support
+  bash_patterns: ['rm\s+-rf']
+  protected_paths: ['^/etc/']
+notifications:
+  desktop:
+    enabled: true
+    volume: 0.5
+"#;
+
+    const TOML_CONFIG: &str = r#"
+[features]
+trust_mode = false
+
+[auto_deny]
+bash_patterns = ['rm\s+-rf']
+protected_paths = ['^/etc/']
+
+[notifications.desktop]
+enabled = true
+volume = 0.5
+"#;
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(ConfigFormat::from_path(Path::new("config.json")), Some(ConfigFormat::Json));
+        assert_eq!(ConfigFormat::from_path(Path::new("config.yaml")), Some(ConfigFormat::Yaml));
+        assert_eq!(ConfigFormat::from_path(Path::new("config.yml")), Some(ConfigFormat::Yaml));
+        assert_eq!(ConfigFormat::from_path(Path::new("config.TOML")), Some(ConfigFormat::Toml));
+        assert_eq!(ConfigFormat::from_path(Path::new("config.ini")), None);
+    }
+
+    #[test]
+    fn test_yaml_and_toml_match_json() {
+        let json = parse_config(JSON_CONFIG, ConfigFormat::Json).unwrap();
+        let yaml = parse_config(YAML_CONFIG, ConfigFormat::Yaml).unwrap();
+        let toml = parse_config(TOML_CONFIG, ConfigFormat::Toml).unwrap();
+
+        assert!(!json.features.trust_mode);
+        assert_eq!(json.auto_deny.bash_patterns.last().map(String::as_str), Some(r"rm\s+-rf"));
+        assert_eq!(yaml, json);
+        assert_eq!(toml, json);
+    }
+
+    #[test]
+    fn test_config_file_round_trip() {
+        // Serializing a config to each format and loading it back gives the same Config
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = parse_config(JSON_CONFIG, ConfigFormat::Json).unwrap();
+        config.auto_approve.bash_patterns.push(r#"^echo\s+"[^"]*"$"#.into());
+        config.ambiguous.llm.cache_ttl_seconds = 600;
+
+        let files = [
+            ("config.json", serde_json::to_string_pretty(&config).unwrap()),
+            ("config.yml", serde_yaml::to_string(&config).unwrap()),
+            ("config.toml", toml::to_string(&config).unwrap()),
+        ];
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            assert_eq!(load_config_file(&path).unwrap(), config, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_shadowed_config_warning() {
+        let dir = get_config_dir();
+        assert_eq!(get_config_paths().iter().position(|p| *p == dir.join("config.yml")), Some(2));
+
+        assert_eq!(shadowed_config_warning(&[]), None);
+        assert_eq!(shadowed_config_warning(&[dir.join("config.yaml")]), None);
+        let warning = shadowed_config_warning(&[dir.join("config.json"), dir.join("config.yml"), dir.join("config.toml")]).unwrap();
+        assert!(warning.contains(&format!("using {}", dir.join("config.json").display())), "{}", warning);
+        assert!(warning.contains("config.yml, ") && warning.ends_with("config.toml"), "{}", warning);
+    }
+
+    #[test]
+    fn test_load_config_file_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, YAML_CONFIG).unwrap();
+
+        let config = load_config_file(&path).unwrap();
+        assert_eq!(config.notifications.desktop.volume, 0.5);
+
+        let bad = dir.path().join("config.ini");
+        fs::write(&bad, "").unwrap();
+        assert!(load_config_file(&bad).is_err());
+    }
+
+    #[test]
+    fn test_event_handler_mapping() {
+        let config = parse_config(
+            r#"{"events": {"TaskFinished": "notify", "PostToolUse": "ignore", "Stop": "ignore"}}"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+
+        assert_eq!(config.event_handler("TaskFinished"), "notify");
+        assert_eq!(config.event_handler("PostToolUse"), "ignore");
+        assert_eq!(config.event_handler("Stop"), "ignore");
+        // Built-in mappings survive the merge, unknown events get the permission check
+        assert_eq!(config.event_handler("Notification"), "notification");
+        assert_eq!(config.event_handler("SomethingNew"), "permission");
+        assert_eq!(Config::default().event_handler("SubagentStop"), "subagent_stop");
+    }
+
+    #[test]
+    fn test_analyzer_minimums() {
+        let analyzer = AnalyzerConfig { recent_window: 0, tail_check: 0, ..Default::default() };
+        assert_eq!(analyzer.recent_window(), 1);
+        assert_eq!(analyzer.tail_check(), 1);
+
+        let analyzer = AnalyzerConfig { recent_window: 5, tail_check: 10, ..Default::default() };
+        assert_eq!(analyzer.tail_check(), 5);
     }
 
     #[test]
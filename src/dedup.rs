@@ -31,6 +31,15 @@ impl Manager {
         self.temp_dir.join(format!("claude-notification-content-{}.lock", session_id))
     }
 
+    /// Get message lock file path (one per assistant message)
+    fn get_message_lock_path(&self, session_id: &str, message_id: &str) -> PathBuf {
+        let id: String = message_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.temp_dir.join(format!("claude-notification-msg-{}-{}.lock", session_id, id))
+    }
+
     /// Phase 1: Early duplicate check (fast, non-blocking)
     /// Returns true if this is likely a duplicate
     pub fn check_early_duplicate(&self, session_id: &str, hook_event: Option<&str>) -> bool {
@@ -132,6 +141,26 @@ impl Manager {
         }
     }
 
+    /// Claim the notification for an assistant message, across Stop and
+    /// Notification events. Returns false if the message was already notified
+    /// (until `cleanup` removes the lock).
+    pub fn acquire_message_lock(&self, session_id: &str, message_id: &str) -> Result<bool, String> {
+        let lock_path = self.get_message_lock_path(session_id, message_id);
+
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        {
+            Ok(mut file) => {
+                let _ = write!(file, "{}", platform::current_timestamp());
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(format!("Failed to create message lock: {}", e)),
+        }
+    }
+
     /// Release content lock
     pub fn release_content_lock(&self, session_id: &str) -> Result<(), String> {
         let lock_path = self.get_content_lock_path(session_id);
@@ -287,6 +316,21 @@ mod tests {
         mgr.release_content_lock(&session_id).unwrap();
     }
 
+    #[test]
+    fn test_message_lock() {
+        let mgr = test_manager();
+        let session_id = unique_session_id();
+
+        // Stop and Notification for the same message: only the first notifies
+        assert!(mgr.acquire_message_lock(&session_id, "msg_01").unwrap());
+        assert!(!mgr.acquire_message_lock(&session_id, "msg_01").unwrap());
+
+        // A different message notifies too
+        assert!(mgr.acquire_message_lock(&session_id, "msg_02").unwrap());
+
+        mgr.cleanup_for_session(&session_id).unwrap();
+    }
+
     #[test]
     fn test_cleanup_for_session() {
         let mgr = test_manager();
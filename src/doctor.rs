@@ -0,0 +1,262 @@
+//! Installation diagnostics (`doctor` subcommand)
+//!
+//! Each check reports pass/warn/fail with a hint on how to fix it, so setup
+//! problems (unwritable dirs, no display, bad webhook URL or LLM key) show up
+//! in one place instead of as silently missing notifications.
+
+use crate::config::{expand_env_vars, find_config_path, get_config_dir, load_config_file, Config};
+use crate::platform;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::time::Duration;
+
+/// Timeout for the network checks
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Result of one diagnostic check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix a warning or failure
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: CheckStatus::Pass, detail: detail.into(), hint: None }
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, hint: &str) -> Self {
+        Self { name: name.into(), status: CheckStatus::Warn, detail: detail.into(), hint: Some(hint.into()) }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, hint: &str) -> Self {
+        Self { name: name.into(), status: CheckStatus::Fail, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.status {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        };
+        write!(f, "[{}] {}: {}", label, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       -> {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// The config file parses (no file means built-in defaults)
+pub fn check_config_readable(path: Option<&Path>) -> Check {
+    let Some(path) = path else {
+        return Check::warn(
+            "config",
+            "no config file found, using built-in defaults",
+            &format!("create {} to customize", get_config_dir().join("config.json").display()),
+        );
+    };
+    match load_config_file(path) {
+        Ok(_) => Check::pass("config", format!("{} loaded", path.display())),
+        Err(e) => Check::fail("config", e, "fix the error above; `lint-config` reports pattern problems"),
+    }
+}
+
+/// A directory exists (or can be created) and accepts new files
+pub fn check_dir_writable(name: &str, dir: &Path) -> Check {
+    if let Err(e) = fs::create_dir_all(dir) {
+        return Check::fail(name, format!("cannot create {}: {}", dir.display(), e), "check the directory's permissions");
+    }
+    let probe = dir.join(format!(".permission-hook-doctor-{}", std::process::id()));
+    match OpenOptions::new().write(true).create(true).truncate(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Check::pass(name, format!("{} is writable", dir.display()))
+        }
+        Err(e) => Check::fail(name, format!("cannot write to {}: {}", dir.display(), e), "check the directory's permissions"),
+    }
+}
+
+/// A graphical session is available for desktop notifications
+pub fn check_display(config: &Config) -> Check {
+    if !config.notifications.desktop.enabled {
+        return Check::pass("display", "desktop notifications disabled");
+    }
+    if cfg!(target_os = "linux") && std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Check::warn(
+            "display",
+            "neither DISPLAY nor WAYLAND_DISPLAY is set",
+            "desktop notifications need a graphical session; use a webhook over SSH",
+        );
+    }
+    Check::pass("display", "available")
+}
+
+/// Custom sound files are usable
+pub fn check_sounds(config: &Config, sounds_dir: &Path) -> Check {
+    if !config.notifications.desktop.sound {
+        return Check::pass("sounds", "sounds disabled");
+    }
+    let custom = fs::read_dir(sounds_dir)
+        .map(|entries| entries.flatten().count())
+        .unwrap_or(0);
+    if custom == 0 {
+        return Check::pass("sounds", "no custom sounds, using system sounds");
+    }
+    if !cfg!(feature = "sound") {
+        return Check::warn(
+            "sounds",
+            format!("{} custom sound file(s) in {}, but built without sound support", custom, sounds_dir.display()),
+            "rebuild with `cargo build --release --features sound`",
+        );
+    }
+    Check::pass("sounds", format!("{} custom sound file(s)", custom))
+}
+
+/// The webhook URL resolves and its host answers a HEAD request
+pub fn check_webhook(config: &Config) -> Check {
+    let webhook = &config.notifications.webhook;
+    if !webhook.enabled {
+        return Check::pass("webhook", "disabled");
+    }
+    if webhook.url.is_empty() {
+        return Check::fail("webhook", "enabled but no url set", "set notifications.webhook.url");
+    }
+    let url = match expand_env_vars(&webhook.url) {
+        Ok(url) => url,
+        Err(e) => return Check::fail("webhook", e, "export the variable or put the URL in the config"),
+    };
+
+    let client = reqwest::blocking::Client::builder().timeout(NETWORK_TIMEOUT).build();
+    // Any HTTP response (even 404/405) means the host is reachable
+    match client.and_then(|c| c.head(&url).send()) {
+        Ok(response) => Check::pass("webhook", format!("reachable (HTTP {})", response.status().as_u16())),
+        Err(e) => Check::fail("webhook", format!("unreachable: {}", e), "check the URL and your network/proxy"),
+    }
+}
+
+/// The LLM endpoint accepts the configured key
+pub fn check_llm(config: &Config) -> Check {
+    if config.ambiguous.mode != "llm" {
+        return Check::pass("llm", "not used (ambiguous.mode is not \"llm\")");
+    }
+    let llm = &config.ambiguous.llm;
+    if llm.api_key.is_empty() {
+        return Check::fail("llm", "ambiguous.mode is \"llm\" but no api_key is set", "set ambiguous.llm.api_key");
+    }
+    let base_url = if llm.base_url.is_empty() { "https://openrouter.ai/api/v1" } else { &llm.base_url };
+
+    let client = reqwest::blocking::Client::builder().timeout(NETWORK_TIMEOUT).build();
+    let response = client.and_then(|c| {
+        c.get(format!("{}/models", base_url))
+            .header("Authorization", format!("Bearer {}", llm.api_key))
+            .send()
+    });
+    match response {
+        Ok(r) if r.status().as_u16() == 401 || r.status().as_u16() == 403 => {
+            Check::fail("llm", format!("{} rejected the api_key", base_url), "check ambiguous.llm.api_key")
+        }
+        Ok(r) => Check::pass("llm", format!("{} reachable (HTTP {})", base_url, r.status().as_u16())),
+        Err(e) => Check::fail("llm", format!("{} unreachable: {}", base_url, e), "check ambiguous.llm.base_url and your network"),
+    }
+}
+
+/// Run the `doctor` subcommand; exits 1 if any check failed
+pub fn run_doctor(config: &Config) -> i32 {
+    let checks = [
+        check_config_readable(find_config_path().as_deref()),
+        check_dir_writable("config dir", &get_config_dir()),
+        check_dir_writable("temp dir", &platform::temp_dir()),
+        check_display(config),
+        check_sounds(config, &get_config_dir().join("sounds")),
+        check_webhook(config),
+        check_llm(config),
+    ];
+
+    for check in &checks {
+        println!("{}", check);
+    }
+    if checks.iter().any(|c| c.status == CheckStatus::Fail) {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    #[test]
+    fn test_check_config_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+
+        fs::write(&path, r#"{"features": {"trust_mode": false}}"#).unwrap();
+        assert_eq!(check_config_readable(Some(&path)).status, CheckStatus::Pass);
+
+        fs::write(&path, "{ not json").unwrap();
+        let check = check_config_readable(Some(&path));
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hint.is_some());
+
+        assert_eq!(check_config_readable(None).status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn test_check_dir_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        assert_eq!(check_dir_writable("config dir", &nested).status, CheckStatus::Pass);
+        assert!(nested.exists());
+        assert_eq!(fs::read_dir(&nested).unwrap().count(), 0);
+
+        // A file where the directory should be
+        let file = dir.path().join("file");
+        fs::write(&file, "x").unwrap();
+        assert_eq!(check_dir_writable("config dir", &file.join("sub")).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_disabled_subsystems_pass_without_network() {
+        let mut config = default_config();
+        config.notifications.webhook.enabled = false;
+        config.ambiguous.mode = "ask".into();
+        assert_eq!(check_webhook(&config).status, CheckStatus::Pass);
+        assert_eq!(check_llm(&config).status, CheckStatus::Pass);
+
+        config.notifications.webhook.enabled = true;
+        config.notifications.webhook.url = String::new();
+        assert_eq!(check_webhook(&config).status, CheckStatus::Fail);
+
+        config.ambiguous.mode = "llm".into();
+        config.ambiguous.llm.api_key = String::new();
+        assert_eq!(check_llm(&config).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_check_sounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = default_config();
+        config.notifications.desktop.sound = true;
+        assert_eq!(check_sounds(&config, dir.path()).status, CheckStatus::Pass);
+
+        fs::write(dir.path().join("alert.wav"), "").unwrap();
+        let expected = if cfg!(feature = "sound") { CheckStatus::Pass } else { CheckStatus::Warn };
+        assert_eq!(check_sounds(&config, dir.path()).status, expected);
+    }
+}
@@ -0,0 +1,180 @@
+//! Dry run of a decision (`--explain`)
+//!
+//! Runs the same pipeline as the hook on a payload and describes what
+//! happened: which patterns each part of a Bash command matched, which step
+//! decided, and the final decision. Nothing is logged, notified or remembered,
+//! and tier 3 (external checker, LLM, approval broker) is skipped, so no
+//! command is run, nothing is sent over the network and no one is waited on.
+
+use crate::config::Config;
+use crate::i18n::reason_code;
+use crate::permission::{
+    check_auto_deny, evaluate, is_auto_approved, segment_matching_pattern, split_command_segments, without_tier3, Decision, HookInput,
+};
+
+/// `list[i] 'pattern'` for a pattern from a config list
+fn pattern_ref(field: &str, list: &[String], pattern: &str) -> String {
+    match list.iter().position(|p| p == pattern) {
+        Some(i) => format!("{}[{}] '{}'", field, i, pattern),
+        None => format!("{} '{}'", field, pattern),
+    }
+}
+
+/// The step that decided, for reasons that name one
+fn deciding_step(reason: &str) -> Option<&'static str> {
+    Some(match reason_code(reason) {
+        "granted" => "GRANT",
+        "remembered" => "REMEMBERED",
+        "approved command hash" => "COMMAND HASH",
+        "trust mode enabled" => "TRUST MODE",
+        "clean tree reset" => "GIT RESET GRACE",
+        "warm-up" => "WARM-UP",
+        "strict mode" => "STRICT MODE",
+        "background command" => "STRICT BACKGROUND",
+        _ => return None,
+    })
+}
+
+/// Human-readable trace of the decision for a payload
+pub fn explain(config: &Config, input: &HookInput) -> String {
+    let config = &without_tier3(config);
+    let tool_name = input.get_tool_name();
+    let tool_input = input.get_tool_input();
+    let mut lines = vec![format!("Tool: {}", tool_name)];
+
+    let command = tool_input.get("command").and_then(|c| c.as_str()).filter(|_| tool_name == "Bash");
+    if let Some(command) = command {
+        lines.push(format!("Command: {}", command));
+        for segment in split_command_segments(command) {
+            let segment = segment.trim();
+            if segment.is_empty() {
+                continue;
+            }
+            let approve = segment_matching_pattern(segment, &config.auto_approve.bash_patterns)
+                .map(|p| pattern_ref("auto_approve.bash_patterns", &config.auto_approve.bash_patterns, &p));
+            let deny = segment_matching_pattern(segment, &config.auto_deny.bash_patterns)
+                .map(|p| pattern_ref("auto_deny.bash_patterns", &config.auto_deny.bash_patterns, &p));
+            let matched: Vec<String> = approve.into_iter().chain(deny).collect();
+            let matched = if matched.is_empty() { "no pattern".to_string() } else { matched.join(", ") };
+            lines.push(format!("  segment '{}': {}", segment, matched));
+        }
+    }
+
+    let decision = evaluate(config, input);
+    let approved = is_auto_approved(config, &tool_name, &tool_input, &input.get_cwd());
+    let denied = check_auto_deny(config, &tool_name, &tool_input, &input.get_cwd());
+    let (outcome, reason) = match &decision {
+        Decision::Allow { reason } => ("ALLOW", reason),
+        Decision::Deny { reason, .. } => ("DENY", reason),
+        Decision::Prompt { reason } => ("PROMPT", reason),
+    };
+    let verdict = match &decision {
+        _ if deciding_step(reason).is_some() => format!("{} {}: {}", deciding_step(reason).unwrap_or_default(), outcome, reason),
+        Decision::Allow { .. } if approved.as_ref() == Some(reason) => format!("TIER 1 ALLOW: {}", reason),
+        Decision::Deny { .. } if denied.as_ref().is_some_and(|m| m.describe() == *reason) => {
+            let custom = denied.as_ref().and_then(|m| m.custom_reason.clone());
+            let pattern = denied.and_then(|m| m.pattern).filter(|p| config.auto_deny.bash_patterns.contains(p));
+            match (pattern, custom) {
+                (Some(p), Some(custom)) => format!(
+                    "TIER 2 DENY: matched {}: {}",
+                    pattern_ref("auto_deny.bash_patterns", &config.auto_deny.bash_patterns, &p),
+                    custom
+                ),
+                (Some(p), None) => format!("TIER 2 DENY: matched {}", pattern_ref("auto_deny.bash_patterns", &config.auto_deny.bash_patterns, &p)),
+                (None, _) => format!("TIER 2 DENY: {}", reason),
+            }
+        }
+        Decision::Prompt { .. } if reason.starts_with("Prompting user for") => {
+            format!("NO RULE PROMPT: {} (tier 3 is skipped by --explain)", reason)
+        }
+        // Tier 3 is off here, so anything else is a check that runs before the tiers
+        _ => format!("CHECK {}: {}", outcome, reason),
+    };
+    lines.push(verdict);
+
+    let outcome = match decision {
+        Decision::Allow { .. } => "allow (JSON on stdout, exit 0)",
+        Decision::Deny { .. } => "deny (exit 2)",
+        Decision::Prompt { .. } => "prompt (no output, exit 0)",
+    };
+    lines.push(format!("Decision: {}", outcome));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    fn payload(tool: &str, tool_input: serde_json::Value) -> HookInput {
+        serde_json::from_value(serde_json::json!({"tool_name": tool, "tool_input": tool_input})).unwrap()
+    }
+
+    #[test]
+    fn test_explain_deny() {
+        let config = default_config();
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "ls && git push --force"})));
+        let index = config.auto_deny.bash_patterns.iter().position(|p| p == r"git\s+push.*--force").unwrap();
+
+        assert!(trace.contains("segment 'ls': auto_approve.bash_patterns["), "{}", trace);
+        assert!(trace.contains(&format!("TIER 2 DENY: matched auto_deny.bash_patterns[{}]", index)), "{}", trace);
+        assert!(trace.ends_with("Decision: deny (exit 2)"), "{}", trace);
+    }
+
+    #[test]
+    fn test_explain_custom_deny_reason() {
+        let mut config = default_config();
+        config.auto_deny.deny_reasons.insert(r"git\s+push.*--force".into(), "Use --force-with-lease".into());
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "git push --force"})));
+        assert!(trace.contains("'git\\s+push.*--force': Use --force-with-lease"), "{}", trace);
+    }
+
+    #[test]
+    fn test_explain_allow_and_prompt() {
+        let mut config = default_config();
+        config.features.trust_mode = false;
+
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "git status"})));
+        assert!(trace.contains("TIER 1 ALLOW: safe pattern"), "{}", trace);
+
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "make deploy"})));
+        assert!(trace.contains("segment 'make deploy': no pattern"), "{}", trace);
+        assert!(trace.contains("NO RULE PROMPT"), "{}", trace);
+
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "find . -exec sh \\;"})));
+        assert!(trace.contains("CHECK PROMPT: prompt pattern"), "{}", trace);
+    }
+
+    #[test]
+    fn test_explain_names_the_deciding_step() {
+        let mut config = default_config();
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "make deploy"})));
+        assert!(trace.contains("TRUST MODE ALLOW: trust mode enabled"), "{}", trace);
+
+        config.features.trust_mode = false;
+        config.features.strict_mode = true;
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "make deploy"})));
+        assert!(trace.contains("STRICT MODE DENY: strict mode"), "{}", trace);
+
+        // Grants and remembered commands aren't a tier of their own
+        assert_eq!(deciding_step("granted"), Some("GRANT"));
+        assert_eq!(deciding_step("remembered"), Some("REMEMBERED"));
+        assert_eq!(deciding_step("approved command hash 'abc' overriding dangerous pattern 'x'"), Some("COMMAND HASH"));
+        assert_eq!(deciding_step("safe pattern"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_explain_skips_tier3() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let mut config = default_config();
+        config.features.trust_mode = false;
+        config.ambiguous.external_checker = format!("touch {}", marker.display());
+        config.ambiguous.approval_broker_dir = dir.path().to_string_lossy().to_string();
+
+        let trace = explain(&config, &payload("Bash", serde_json::json!({"command": "make deploy"})));
+        assert!(trace.contains("NO RULE PROMPT"), "{}", trace);
+        assert!(!marker.exists());
+    }
+}
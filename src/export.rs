@@ -0,0 +1,234 @@
+//! Decision log export (`export` subcommand)
+//!
+//! Filters `decisions.log` to a timestamp range and writes the rows as CSV or
+//! JSON for audits. Rows may use the short decision codes (`Y`/`N`/`ASK`) or
+//! the full words, and JSON lines (`decision-inputs.jsonl`) are read too.
+
+use crate::logging::{escape_csv, CSV_HEADER};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// One decision log row
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub tool: String,
+    /// "allow", "deny" or "prompt"
+    pub decision: String,
+    pub reason: String,
+    pub details: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Split a CSV line into fields, undoing `escape_csv` quoting
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Full decision word for a log code
+fn decision_word(code: &str) -> String {
+    match code {
+        "Y" => "allow".into(),
+        "N" => "deny".into(),
+        "ASK" => "prompt".into(),
+        other => other.to_lowercase(),
+    }
+}
+
+/// Parse one log line (CSV row or JSON object); None for the header and junk
+pub fn parse_line(line: &str) -> Option<LogEntry> {
+    let line = line.trim();
+    if line.is_empty() || line == CSV_HEADER {
+        return None;
+    }
+
+    if line.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let field = |key: &str| value.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+        return Some(LogEntry {
+            timestamp: value.get("timestamp")?.as_str()?.to_string(),
+            tool: field("tool"),
+            decision: decision_word(&field("decision")),
+            reason: field("reason"),
+            details: value.get("tool_input").map(|v| v.to_string()).unwrap_or_default(),
+        });
+    }
+
+    let mut fields = split_csv(line).into_iter();
+    let timestamp = fields.next()?;
+    parse_timestamp(&timestamp).ok()?;
+    Some(LogEntry {
+        timestamp,
+        tool: fields.next()?,
+        decision: decision_word(&fields.next()?),
+        reason: fields.next().unwrap_or_default(),
+        details: fields.next().unwrap_or_default(),
+    })
+}
+
+/// Accept `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SS`
+fn parse_timestamp(ts: &str) -> Result<(), String> {
+    if NaiveDateTime::parse_from_str(ts, "%Y-%m-%dT%H:%M:%S").is_ok() || NaiveDate::parse_from_str(ts, "%Y-%m-%d").is_ok() {
+        Ok(())
+    } else {
+        Err(format!("Invalid timestamp '{}': expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS", ts))
+    }
+}
+
+/// Whether a row falls in `[from, to]`. Both ends are inclusive, and a
+/// date-only `to` covers that whole day.
+fn in_range(timestamp: &str, from: Option<&str>, to: Option<&str>) -> bool {
+    let before_start = matches!(from, Some(from) if timestamp < from);
+    let after_end = matches!(to, Some(to) if timestamp.get(..to.len()).unwrap_or(timestamp) > to);
+    !before_start && !after_end
+}
+
+/// Rows of a log whose timestamps fall in the range
+pub fn filter_entries(content: &str, from: Option<&str>, to: Option<&str>) -> Vec<LogEntry> {
+    content
+        .lines()
+        .filter_map(parse_line)
+        .filter(|entry| in_range(&entry.timestamp, from, to))
+        .collect()
+}
+
+/// Render rows in the export format
+pub fn render(entries: &[LogEntry], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(entries).unwrap_or_else(|_| "[]".into()),
+        ExportFormat::Csv => {
+            let mut out = String::from(CSV_HEADER);
+            for e in entries {
+                out.push('\n');
+                out.push_str(&[&e.timestamp, &e.tool, &e.decision, &e.reason, &e.details].map(|f| escape_csv(f)).join(","));
+            }
+            out
+        }
+    }
+}
+
+/// Run `export [--from <ts>] [--to <ts>] [--format csv|json]` over `log_path`
+pub fn run_export(args: &[String], log_path: &Path) -> i32 {
+    let usage = "Usage: claude-permission-hook export [--project <dir>] [--from <ts>] [--to <ts>] [--format csv|json]";
+    let mut from = None;
+    let mut to = None;
+    let mut format = ExportFormat::Csv;
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let Some(value) = iter.next() else {
+            eprintln!("{}", usage);
+            return 1;
+        };
+        match flag.as_str() {
+            "--from" | "--to" => {
+                if let Err(e) = parse_timestamp(value) {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+                if flag == "--from" {
+                    from = Some(value.as_str());
+                } else {
+                    to = Some(value.as_str());
+                }
+            }
+            "--format" => match value.as_str() {
+                "csv" => format = ExportFormat::Csv,
+                "json" => format = ExportFormat::Json,
+                other => {
+                    eprintln!("Unknown format '{}': use csv or json", other);
+                    return 1;
+                }
+            },
+            _ => {
+                eprintln!("{}", usage);
+                return 1;
+            }
+        }
+    }
+
+    let content = match fs::read_to_string(log_path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", log_path.display(), e);
+            return 1;
+        }
+    };
+    println!("{}", render(&filter_entries(&content, from, to), format));
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "\
+timestamp,tool,decision,reason,details
+2026-03-01T09:00:00,Bash,Y,safe pattern,git status
+2026-03-02T10:30:00,Bash,N,dangerous pattern,\"rm -rf /, now\"
+2026-03-02T23:59:59,Write,ASK,prompt path,.env
+2026-03-03T00:00:01,Read,allow,safe tool,src/main.rs
+{\"timestamp\":\"2026-03-02T12:00:00\",\"tool\":\"Bash\",\"decision\":\"allow\",\"tool_input\":{\"command\":\"ls\"}}
+not a log line
+";
+
+    #[test]
+    fn test_filter_entries_in_range() {
+        let entries = filter_entries(LOG, Some("2026-03-02"), Some("2026-03-02"));
+        let stamps: Vec<&str> = entries.iter().map(|e| e.timestamp.as_str()).collect();
+        assert_eq!(stamps, ["2026-03-02T10:30:00", "2026-03-02T23:59:59", "2026-03-02T12:00:00"]);
+
+        assert_eq!(entries[0].decision, "deny");
+        assert_eq!(entries[0].details, "rm -rf /, now");
+        assert_eq!(entries[1].decision, "prompt");
+        assert_eq!(entries[2].details, r#"{"command":"ls"}"#);
+
+        assert_eq!(filter_entries(LOG, None, None).len(), 5);
+        assert_eq!(filter_entries(LOG, Some("2026-03-02T23:00:00"), None).len(), 2);
+        assert_eq!(filter_entries(LOG, None, Some("2026-03-01T09:00:00")).len(), 1);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let entries = filter_entries(LOG, Some("2026-03-02T10:00:00"), Some("2026-03-02T11:00:00"));
+        assert_eq!(
+            render(&entries, ExportFormat::Csv),
+            "timestamp,tool,decision,reason,details\n2026-03-02T10:30:00,Bash,deny,dangerous pattern,\"rm -rf /, now\""
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&render(&entries, ExportFormat::Json)).unwrap();
+        assert_eq!(json[0]["reason"], "dangerous pattern");
+        assert_eq!(json.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert!(parse_timestamp("2026-03-02").is_ok());
+        assert!(parse_timestamp("2026-03-02T10:30:00").is_ok());
+        assert!(parse_timestamp("yesterday").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Time-boxed command grants (`grant` subcommand)
+//!
+//! A grant authorizes one command pattern for a few minutes without editing
+//! the config, e.g. `claude-permission-hook grant '^npm\s+install' 10`.
+//! Grants only stand in for a prompt: a command an auto-deny rule matches is
+//! denied whatever the grants say.
+
+use crate::config::get_config_dir;
+use crate::patterns;
+use crate::platform;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_GRANT_MINUTES: i64 = 10;
+
+/// A command pattern approved until `expires_at` (Unix seconds)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Grant {
+    pub pattern: String,
+    pub expires_at: i64,
+}
+
+impl Grant {
+    pub fn is_active(&self, now: i64) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// Grants file, shared by all sessions. It lives in the config dir, which is
+/// a protected path, so the agent can't grant itself anything
+pub fn grants_path() -> PathBuf {
+    get_config_dir().join("grants.json")
+}
+
+/// A grant must name the command it approves: anchored with `^` and starting
+/// with a literal, so patterns like `.*` or `^.*` can't approve everything
+pub fn check_grant_pattern(pattern: &str) -> Result<(), String> {
+    patterns::compile(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+    let Some(rest) = pattern.strip_prefix('^') else {
+        return Err(format!("Grant pattern '{}' must be anchored with ^, e.g. '^npm\\s+install'", pattern));
+    };
+    let literal = rest.starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '/') || rest.starts_with("\\.");
+    if !literal {
+        return Err(format!("Grant pattern '{}' must start with the command name after ^", pattern));
+    }
+    Ok(())
+}
+
+/// Load grants from a file; a missing or unreadable file means no grants
+pub fn load_grants(path: &Path) -> Vec<Grant> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Active grants from the shared grants file (entries with patterns `grant`
+/// would refuse are ignored)
+pub fn active_grants(now: i64) -> Vec<Grant> {
+    load_grants(&grants_path())
+        .into_iter()
+        .filter(|g| g.is_active(now) && check_grant_pattern(&g.pattern).is_ok())
+        .collect()
+}
+
+/// Add a grant for `minutes`, dropping expired ones from the file
+pub fn add_grant(path: &Path, pattern: &str, minutes: i64, now: i64) -> Result<Grant, String> {
+    check_grant_pattern(pattern)?;
+    if minutes <= 0 {
+        return Err("Grant duration must be at least 1 minute".into());
+    }
+
+    let expires_at = minutes
+        .checked_mul(60)
+        .and_then(|seconds| now.checked_add(seconds))
+        .ok_or_else(|| format!("Grant duration of {} minutes is too large", minutes))?;
+
+    let mut grants: Vec<Grant> = load_grants(path).into_iter().filter(|g| g.is_active(now)).collect();
+    let grant = Grant { pattern: pattern.to_string(), expires_at };
+    grants.retain(|g| g.pattern != grant.pattern);
+    grants.push(grant.clone());
+
+    let content = serde_json::to_string_pretty(&grants).map_err(|e| e.to_string())?;
+    platform::write_private_file(path, &content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(grant)
+}
+
+/// Run the `grant` subcommand: `grant <pattern> [minutes]`, or list active grants
+pub fn run_grant(args: &[String]) -> i32 {
+    let now = platform::current_timestamp();
+
+    let Some(pattern) = args.first() else {
+        let grants = active_grants(now);
+        if grants.is_empty() {
+            println!("No active grants");
+        }
+        for grant in grants {
+            println!("{}  ({} min left)", grant.pattern, (grant.expires_at - now + 59) / 60);
+        }
+        return 0;
+    };
+
+    let minutes = match args.get(1).map(|m| m.parse::<i64>()) {
+        None => DEFAULT_GRANT_MINUTES,
+        Some(Ok(m)) => m,
+        Some(Err(_)) => {
+            eprintln!("Usage: claude-permission-hook grant <pattern> [minutes]");
+            return 1;
+        }
+    };
+
+    match add_grant(&grants_path(), pattern, minutes, now) {
+        Ok(_) => {
+            println!("Granted '{}' for {} minutes", pattern, minutes);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_load_grants() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("grants.json");
+
+        let grant = add_grant(&path, r"^npm\s+install", 10, 1_000).unwrap();
+        assert_eq!(grant.expires_at, 1_600);
+        assert_eq!(load_grants(&path), vec![grant.clone()]);
+        assert!(grant.is_active(1_599));
+        assert!(!grant.is_active(1_600));
+
+        // Re-granting refreshes the window; expired grants are dropped
+        add_grant(&path, r"^cargo\s+publish", 1, 1_000).unwrap();
+        add_grant(&path, r"^npm\s+install", 10, 1_200).unwrap();
+        let grants = load_grants(&path);
+        assert_eq!(grants.len(), 1);
+        assert_eq!(grants[0].expires_at, 1_800);
+    }
+
+    #[test]
+    fn test_add_grant_rejects_bad_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("grants.json");
+
+        assert!(add_grant(&path, "([", 10, 0).is_err());
+        assert!(add_grant(&path, "^ls", 0, 0).is_err());
+        // Durations whose expiry would overflow
+        assert!(add_grant(&path, "^ls", i64::MAX, 0).is_err());
+        assert!(add_grant(&path, "^ls", i64::MAX / 60, 1_000).is_err());
+        // Unanchored and match-anything patterns
+        for pattern in [".*", "npm install", "^.*", "^.", "^\\s*rm", "^(rm|ls)", "^[a-z]+"] {
+            assert!(add_grant(&path, pattern, 10, 0).is_err(), "{} should be rejected", pattern);
+        }
+        assert!(load_grants(&path).is_empty());
+
+        for pattern in ["^npm\\s+install", "^\\./deploy\\.sh", "^/usr/bin/make", "^_build"] {
+            assert!(check_grant_pattern(pattern).is_ok(), "{}", pattern);
+        }
+    }
+}
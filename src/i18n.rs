@@ -0,0 +1,90 @@
+//! Localized user-facing messages
+//!
+//! Messages live in an embedded catalog (`messages.json`) keyed by locale and
+//! message id. Deny reasons use their machine reason code (e.g.
+//! "dangerous pattern") as the id, so codes in logs stay the same in every locale.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+type Catalog = HashMap<String, HashMap<String, String>>;
+
+const CATALOG_JSON: &str = include_str!("messages.json");
+
+fn catalog() -> &'static Catalog {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG.get_or_init(|| serde_json::from_str(CATALOG_JSON).unwrap_or_default())
+}
+
+/// Locales to try in order: exact ("pt-br"), language ("pt"), then English
+fn locale_chain(locale: &str) -> Vec<String> {
+    let locale = locale.trim().to_lowercase().replace('_', "-");
+    let mut chain = Vec::new();
+    if !locale.is_empty() {
+        if let Some((language, _)) = locale.split_once('-') {
+            chain.push(locale.clone());
+            chain.push(language.to_string());
+        } else {
+            chain.push(locale);
+        }
+    }
+    chain.push("en".to_string());
+    chain
+}
+
+/// Look up a message, falling back to English; None if the id is unknown
+pub fn translate(locale: &str, id: &str) -> Option<&'static str> {
+    let catalog = catalog();
+    locale_chain(locale)
+        .iter()
+        .find_map(|l| catalog.get(l).and_then(|messages| messages.get(id)))
+        .map(String::as_str)
+}
+
+/// The code a reason starts with. Details such as the matched pattern follow
+/// in quotes: `dangerous pattern 'rm\s+-rf' matched in segment 'rm -rf /'`
+pub fn reason_code(reason: &str) -> &str {
+    reason.split(" '").next().unwrap_or(reason)
+}
+
+/// Render a reason in the given locale: its code translated (the code itself
+/// if untranslated), details as they are
+pub fn localize_reason(locale: &str, reason: &str) -> String {
+    let code = reason_code(reason);
+    format!("{}{}", translate(locale, code).unwrap_or(code), &reason[code.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_parses() {
+        assert!(catalog().contains_key("en"));
+        assert_eq!(translate("en", "alert.title"), Some("BLOCKED"));
+    }
+
+    #[test]
+    fn test_localize_reason() {
+        assert_eq!(localize_reason("es", "dangerous pattern"), "patrón peligroso");
+        assert_eq!(localize_reason("de_DE", "protected path"), "geschützter Pfad");
+        // English and unknown locales render the code itself
+        assert_eq!(localize_reason("en", "dangerous pattern"), "dangerous pattern");
+        assert_eq!(localize_reason("xx", "protected path"), "protected path");
+        // Untranslated reasons pass through
+        assert_eq!(localize_reason("es", "safe pattern"), "safe pattern");
+        // Details after the code are kept
+        assert_eq!(
+            localize_reason("es", "dangerous pattern 'rm' matched in segment 'rm -rf /'"),
+            "patrón peligroso 'rm' matched in segment 'rm -rf /'"
+        );
+        assert_eq!(reason_code("dangerous pattern 'rm' matched in segment 'rm x'"), "dangerous pattern");
+    }
+
+    #[test]
+    fn test_translate_falls_back_to_english() {
+        assert_eq!(translate("es-MX", "alert.title"), Some("BLOQUEADO"));
+        assert_eq!(translate("ja", "alert.title"), Some("BLOCKED"));
+        assert_eq!(translate("es", "no.such.id"), None);
+    }
+}
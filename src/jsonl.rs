@@ -1,9 +1,9 @@
 //! JSONL transcript parser for Claude Code transcripts
 
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs;
 use std::path::Path;
+use std::time::Duration;
 
 /// A message in the Claude Code transcript
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,11 +14,17 @@ pub struct Message {
     pub message: MessageContent,
     #[serde(default)]
     pub timestamp: String,
+    /// Transcript entry id
+    #[serde(default)]
+    pub uuid: String,
 }
 
 /// Content of a message
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MessageContent {
+    /// API message id (`msg_...`), shared by the entries of one assistant turn
+    #[serde(default)]
+    pub id: String,
     #[serde(default)]
     pub role: String,
     #[serde(default)]
@@ -68,6 +74,11 @@ impl Message {
             .join("\n")
     }
 
+    /// The message's id (API id, else transcript uuid), if the transcript has one
+    pub fn message_id(&self) -> Option<&str> {
+        [self.message.id.as_str(), self.uuid.as_str()].into_iter().find(|id| !id.is_empty())
+    }
+
     /// Get tool input by tool name
     pub fn get_tool_input(&self, tool_name: &str) -> Option<&serde_json::Value> {
         self.message.content
@@ -77,37 +88,91 @@ impl Message {
     }
 }
 
+/// How often to re-read a transcript whose last line is still being written
+const PARTIAL_LINE_RETRIES: u32 = 3;
+const PARTIAL_LINE_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Check whether a line is a complete JSON object: starts with `{` and its
+/// braces balance (ignoring braces inside strings)
+pub fn is_complete_json_line(line: &str) -> bool {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return false;
+    }
+
+    let mut depth: i64 = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth == 0 && !in_string && line.ends_with('}')
+}
+
+/// Parsed transcript lines
+#[derive(Debug, Default)]
+pub struct ParsedTranscript {
+    pub messages: Vec<Message>,
+    /// The last line, if it's an incomplete JSON object (still being appended)
+    pub partial_last_line: Option<String>,
+}
+
+/// Parse JSONL transcript content. Malformed lines in the middle are skipped
+/// (other formats); an incomplete last line is reported instead.
+pub fn parse_transcript_content(content: &str) -> ParsedTranscript {
+    let lines: Vec<&str> = content.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let mut parsed = ParsedTranscript::default();
+
+    for (i, line) in lines.iter().enumerate() {
+        if i == lines.len() - 1 && !is_complete_json_line(line) {
+            parsed.partial_last_line = Some(line.to_string());
+            break;
+        }
+        if let Ok(msg) = serde_json::from_str::<Message>(line) {
+            parsed.messages.push(msg);
+        }
+    }
+
+    parsed
+}
+
 /// Parse a JSONL transcript file
+///
+/// If the last line is still being written, the file is re-read briefly; a
+/// line that never completes is dropped, so the status comes from the last
+/// complete message.
 pub fn parse_transcript(path: &str) -> Result<Vec<Message>, String> {
     let path = Path::new(path);
     if !path.exists() {
         return Err(format!("Transcript file not found: {}", path.display()));
     }
 
-    let file = File::open(path)
-        .map_err(|e| format!("Failed to open transcript: {}", e))?;
-
-    let reader = BufReader::new(file);
-    let mut messages = Vec::new();
+    let mut attempts = 0;
+    loop {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read transcript: {}", e))?;
+        let parsed = parse_transcript_content(&content);
 
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        let line = line.trim();
-
-        if line.is_empty() {
-            continue;
-        }
-
-        match serde_json::from_str::<Message>(line) {
-            Ok(msg) => messages.push(msg),
-            Err(_) => {
-                // Skip malformed lines - they might be partial or different format
-                continue;
-            }
+        if parsed.partial_last_line.is_none() || attempts >= PARTIAL_LINE_RETRIES {
+            return Ok(parsed.messages);
         }
+        attempts += 1;
+        std::thread::sleep(PARTIAL_LINE_RETRY_DELAY);
     }
-
-    Ok(messages)
 }
 
 /// Get assistant messages after the last user message
@@ -125,6 +190,11 @@ pub fn get_recent_assistant_messages(messages: &[Message], max_count: usize) ->
         .collect()
 }
 
+/// Id of the last assistant message, for deduplicating notifications about the same turn
+pub fn last_assistant_message_id(messages: &[Message]) -> Option<String> {
+    messages.iter().rev().find(|m| m.is_assistant())?.message_id().map(String::from)
+}
+
 /// Get the last N assistant messages regardless of user messages
 pub fn get_last_assistant_messages(messages: &[Message], count: usize) -> Vec<&Message> {
     messages.iter()
@@ -145,6 +215,7 @@ mod tests {
         Message {
             msg_type: "user".into(),
             message: MessageContent {
+                id: String::new(),
                 role: "user".into(),
                 content: vec![Content {
                     content_type: "text".into(),
@@ -154,6 +225,7 @@ mod tests {
                 }],
             },
             timestamp: "2025-01-01T12:00:00Z".into(),
+            uuid: String::new(),
         }
     }
 
@@ -179,10 +251,12 @@ mod tests {
         Message {
             msg_type: "assistant".into(),
             message: MessageContent {
+                id: String::new(),
                 role: "assistant".into(),
                 content,
             },
             timestamp: "2025-01-01T12:00:01Z".into(),
+            uuid: String::new(),
         }
     }
 
@@ -243,4 +317,53 @@ mod tests {
         assert_eq!(last[0].get_tools(), vec!["Write"]);
         assert_eq!(last[1].get_tools(), vec!["Bash"]);
     }
+
+    const USER_LINE: &str = r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"Fix it {now}"}]}}"#;
+    const ASSISTANT_LINE: &str = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Done. Anything else?"}]}}"#;
+
+    #[test]
+    fn test_last_assistant_message_id() {
+        let content = concat!(
+            r#"{"type":"user","uuid":"u-1","message":{"role":"user","content":[]}}"#, "\n",
+            r#"{"type":"assistant","uuid":"a-1","message":{"id":"msg_01","role":"assistant","content":[]}}"#, "\n",
+            r#"{"type":"assistant","uuid":"a-2","message":{"role":"assistant","content":[]}}"#, "\n",
+        );
+        let messages = parse_transcript_content(content).messages;
+        assert_eq!(messages[1].message_id(), Some("msg_01"));
+        assert_eq!(last_assistant_message_id(&messages).as_deref(), Some("a-2"));
+
+        assert_eq!(last_assistant_message_id(&[create_assistant_message(&[], "hi")]), None);
+    }
+
+    #[test]
+    fn test_is_complete_json_line() {
+        assert!(is_complete_json_line(USER_LINE));
+        assert!(is_complete_json_line(r#"{"text":"brace } and quote \" inside"}"#));
+        assert!(!is_complete_json_line(&USER_LINE[..USER_LINE.len() - 3]));
+        assert!(!is_complete_json_line(r#"{"text":"unterminated }"#));
+        assert!(!is_complete_json_line("not json"));
+    }
+
+    #[test]
+    fn test_parse_transcript_truncated_last_line() {
+        let truncated = &ASSISTANT_LINE[..40];
+        let content = format!("{}\n{}\n{}", USER_LINE, ASSISTANT_LINE, truncated);
+
+        let parsed = parse_transcript_content(&content);
+        assert_eq!(parsed.messages.len(), 2);
+        assert_eq!(parsed.partial_last_line.as_deref(), Some(truncated));
+
+        // A file that never completes drops the partial line after retrying
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transcript.jsonl");
+        std::fs::write(&path, &content).unwrap();
+        let messages = parse_transcript(path.to_str().unwrap()).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].get_text(), "Done. Anything else?");
+
+        // Complete transcripts have no partial line
+        let parsed = parse_transcript_content(&format!("{}\n{}\n", USER_LINE, ASSISTANT_LINE));
+        assert_eq!(parsed.messages.len(), 2);
+        assert!(parsed.partial_last_line.is_none());
+    }
 }
@@ -12,3 +12,19 @@ pub mod notifier;
 pub mod audio;
 pub mod webhook;
 pub mod update;
+pub mod lint;
+pub mod watch;
+pub mod output;
+pub mod grant;
+pub mod i18n;
+pub mod anomaly;
+pub mod patterns;
+pub mod doctor;
+pub mod export;
+pub mod pathglob;
+pub mod explain;
+pub mod broker;
+pub mod llm_cache;
+pub mod remember;
+#[cfg(test)]
+mod test_http;
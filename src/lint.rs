@@ -0,0 +1,414 @@
+//! Configuration linter - flags duplicate, invalid, and overlapping patterns,
+//! malformed command hashes, plus webhook presets that don't match their URL
+
+use crate::config::Config;
+use crate::patterns;
+use crate::webhook::preset_url_mismatch;
+
+/// Kind of problem found in the configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    Duplicate,
+    InvalidRegex,
+    Overlap,
+    InvalidHash,
+    WebhookPreset,
+}
+
+impl LintKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintKind::Duplicate => "duplicate",
+            LintKind::InvalidRegex => "invalid-regex",
+            LintKind::Overlap => "overlap",
+            LintKind::InvalidHash => "invalid-hash",
+            LintKind::WebhookPreset => "webhook-preset",
+        }
+    }
+}
+
+/// A single lint finding with an actionable suggestion
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    pub kind: LintKind,
+    pub field: String,
+    pub message: String,
+    pub suggestion: String,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}\n    -> {}", self.kind.as_str(), self.field, self.message, self.suggestion)
+    }
+}
+
+/// All regex pattern lists in the config, with their config field names
+fn pattern_lists(config: &Config) -> Vec<(&'static str, &Vec<String>)> {
+    vec![
+        ("auto_approve.bash_patterns", &config.auto_approve.bash_patterns),
+        ("auto_deny.bash_patterns", &config.auto_deny.bash_patterns),
+        ("auto_deny.protected_paths", &config.auto_deny.protected_paths),
+        ("auto_deny.shell_escape_patterns", &config.auto_deny.shell_escape_patterns),
+        ("ambiguous.prompt_paths", &config.ambiguous.prompt_paths),
+        ("ambiguous.prompt_patterns", &config.ambiguous.prompt_patterns),
+        ("notifications.watch_patterns", &config.notifications.watch_patterns),
+        ("inline_scripts.dangerous_python_patterns", &config.inline_scripts.dangerous_python_patterns),
+        ("inline_scripts.dangerous_node_patterns", &config.inline_scripts.dangerous_node_patterns),
+        ("inline_scripts.dangerous_powershell_patterns", &config.inline_scripts.dangerous_powershell_patterns),
+        ("inline_scripts.dangerous_cmd_patterns", &config.inline_scripts.dangerous_cmd_patterns),
+    ]
+}
+
+/// Patterns that don't compile, in any pattern list (`--validate`)
+pub fn invalid_patterns(config: &Config) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    for (field, patterns) in pattern_lists(config) {
+        for (i, pattern) in patterns.iter().enumerate() {
+            if let Err(e) = patterns::compile(pattern) {
+                issues.push(LintIssue {
+                    kind: LintKind::InvalidRegex,
+                    field: format!("{}[{}]", field, i),
+                    message: format!("'{}' does not compile: {}", pattern, regex_error_summary(&e)),
+                    suggestion: "fix the regex - invalid patterns are silently skipped at runtime".into(),
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Number of patterns `invalid_patterns` checks
+pub fn pattern_count(config: &Config) -> usize {
+    pattern_lists(config).iter().map(|(_, patterns)| patterns.len()).sum()
+}
+
+/// Lint the configuration and return all issues found
+pub fn lint_config(config: &Config) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+
+    // Duplicate tool names
+    check_duplicates("auto_approve.tools", &config.auto_approve.tools, &mut issues);
+    check_duplicates("auto_deny.tools", &config.auto_deny.tools, &mut issues);
+
+    for (field, patterns) in pattern_lists(config) {
+        check_duplicates(field, patterns, &mut issues);
+    }
+    issues.extend(invalid_patterns(config));
+
+    check_overlaps(config, &mut issues);
+
+    let hashes = &config.auto_approve.approved_command_hashes;
+    check_duplicates("auto_approve.approved_command_hashes", hashes, &mut issues);
+    for (i, hash) in hashes.iter().enumerate() {
+        let hash = hash.trim();
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            issues.push(LintIssue {
+                kind: LintKind::InvalidHash,
+                field: format!("auto_approve.approved_command_hashes[{}]", i),
+                message: format!("'{}' is not a SHA-256 hash (64 hex digits)", hash),
+                suggestion: "use the output of `claude-permission-hook hash-command \"<command>\"`".into(),
+            });
+        }
+    }
+
+    let webhook = &config.notifications.webhook;
+    if let Some(message) = preset_url_mismatch(&webhook.preset, &webhook.url) {
+        issues.push(LintIssue {
+            kind: LintKind::WebhookPreset,
+            field: "notifications.webhook.preset".into(),
+            message,
+            suggestion: "match the preset to the webhook URL (ignore if the URL is a proxy)".into(),
+        });
+    }
+
+    issues
+}
+
+/// Report entries that appear more than once in a list
+fn check_duplicates(field: &str, entries: &[String], issues: &mut Vec<LintIssue>) {
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(first) = entries[..i].iter().position(|e| e == entry) {
+            issues.push(LintIssue {
+                kind: LintKind::Duplicate,
+                field: format!("{}[{}]", field, i),
+                message: format!("'{}' duplicates {}[{}]", entry, field, first),
+                suggestion: format!("remove {}[{}]", field, i),
+            });
+        }
+    }
+}
+
+/// Report approve/deny bash patterns that match the same sample command.
+/// Samples are derived from simple patterns only; complex ones are skipped.
+fn check_overlaps(config: &Config, issues: &mut Vec<LintIssue>) {
+    let approve = &config.auto_approve.bash_patterns;
+    let deny = &config.auto_deny.bash_patterns;
+
+    // Approve pattern whose sample is also denied
+    for (i, pattern) in approve.iter().enumerate() {
+        let Some(sample) = sample_for_pattern(pattern) else { continue };
+        if let Some(j) = first_match(deny, &sample) {
+            issues.push(LintIssue {
+                kind: LintKind::Overlap,
+                field: format!("auto_approve.bash_patterns[{}]", i),
+                message: format!("sample '{}' also matches auto_deny.bash_patterns[{}] '{}'", sample, j, deny[j]),
+                suggestion: "narrow the approve pattern so it cannot match dangerous commands".into(),
+            });
+        }
+    }
+
+    // Deny pattern whose sample is approved first (approve is checked before deny)
+    for (j, pattern) in deny.iter().enumerate() {
+        let Some(sample) = sample_for_pattern(pattern) else { continue };
+        if let Some(i) = first_match(approve, &sample) {
+            issues.push(LintIssue {
+                kind: LintKind::Overlap,
+                field: format!("auto_deny.bash_patterns[{}]", j),
+                message: format!(
+                    "sample '{}' is shadowed by auto_approve.bash_patterns[{}] '{}' (approve is checked first)",
+                    sample, i, approve[i]
+                ),
+                suggestion: format!("anchor or narrow auto_approve.bash_patterns[{}] to exclude this command", i),
+            });
+        }
+    }
+}
+
+/// Index of the first compiling pattern that matches the text
+fn first_match(patterns: &[String], text: &str) -> Option<usize> {
+    patterns.iter().position(|p| patterns::compile(p).map(|re| re.is_match(text)).unwrap_or(false))
+}
+
+fn first_line(s: &str) -> String {
+    s.lines().next().unwrap_or(s).trim().to_string()
+}
+
+/// One-line description of why a regex doesn't compile. Syntax errors span
+/// several lines (header, pattern, caret) with the cause on the `error:` line.
+fn regex_error_summary(e: &regex::Error) -> String {
+    match e {
+        regex::Error::Syntax(message) => message
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("error: "))
+            .map(String::from)
+            .unwrap_or_else(|| first_line(message)),
+        other => first_line(&other.to_string()),
+    }
+}
+
+/// Derive a sample string that a simple regex pattern matches.
+/// Returns None when the pattern is too complex or the sample doesn't verify.
+pub fn sample_for_pattern(pattern: &str) -> Option<String> {
+    let re = patterns::compile(pattern).ok()?;
+    let body = pattern.trim_start_matches("(?i)");
+    let chars: Vec<char> = body.chars().collect();
+    let (sample, _) = literalize(&chars, 0)?;
+    let sample = sample.trim().to_string();
+
+    if !sample.is_empty() && re.is_match(&sample) {
+        Some(sample)
+    } else {
+        None
+    }
+}
+
+/// Turn a regex into a literal sample, taking the first alternative of each
+/// group. Returns the sample and the index where parsing stopped.
+fn literalize(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut out = String::new();
+    let mut i = start;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let (piece, next) = match c {
+            '^' | '$' => (String::new(), i + 1),
+            ')' => return Some((out, i)),
+            '|' => {
+                // Top-level alternative: keep the first, skip the rest
+                let end = skip_alternatives(chars, i)?;
+                return Some((out, end));
+            }
+            '(' => {
+                let mut j = i + 1;
+                if chars.get(j) == Some(&'?') {
+                    // Only non-capturing groups are supported
+                    if chars.get(j + 1) != Some(&':') {
+                        return None;
+                    }
+                    j += 2;
+                }
+                let (inner, end) = literalize(chars, j)?;
+                if chars.get(end) != Some(&')') {
+                    return None;
+                }
+                (inner, end + 1)
+            }
+            '[' => {
+                if chars.get(i + 1) == Some(&'^') {
+                    return None;
+                }
+                let close = chars[i + 1..].iter().position(|&ch| ch == ']')? + i + 1;
+                let first = *chars.get(i + 1)?;
+                let first = if first == '\\' { *chars.get(i + 2)? } else { first };
+                (first.to_string(), close + 1)
+            }
+            '\\' => {
+                let escaped = *chars.get(i + 1)?;
+                let piece = match escaped {
+                    's' => " ".to_string(),
+                    'S' | 'w' => "x".to_string(),
+                    'd' => "1".to_string(),
+                    'b' | 'B' => String::new(),
+                    ch if ch.is_ascii_alphanumeric() => return None,
+                    ch => ch.to_string(),
+                };
+                (piece, i + 2)
+            }
+            // Wildcards become a space so ".*" separates words in the sample
+            '.' => (" ".to_string(), i + 1),
+            ch => (ch.to_string(), i + 1),
+        };
+
+        // Apply quantifier to the piece just parsed
+        match chars.get(next) {
+            Some('*') if c == '.' => {
+                out.push_str(&piece);
+                i = next + 1;
+            }
+            Some('?') | Some('*') => i = next + 1,
+            Some('+') => {
+                out.push_str(&piece);
+                i = next + 1;
+            }
+            Some('{') => return None,
+            _ => {
+                out.push_str(&piece);
+                i = next;
+            }
+        }
+    }
+
+    Some((out, i))
+}
+
+/// Skip from a top-level `|` to the end of the current group (or pattern)
+fn skip_alternatives(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    Some(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    #[test]
+    fn test_sample_for_pattern() {
+        assert_eq!(sample_for_pattern(r"^git\s+(status|log|diff)").as_deref(), Some("git status"));
+        assert_eq!(sample_for_pattern(r"^ls(\s|$)").as_deref(), Some("ls"));
+        assert_eq!(sample_for_pattern(r"git\s+reset\s+--hard").as_deref(), Some("git reset --hard"));
+        assert_eq!(sample_for_pattern(r"mkfs\.").as_deref(), Some("mkfs."));
+        assert_eq!(sample_for_pattern(r"git\s+push.*--force").as_deref(), Some("git push --force"));
+        assert!(sample_for_pattern(r"[^a]+").is_none());
+    }
+
+    #[test]
+    fn test_default_config_is_clean() {
+        let issues = lint_config(&default_config());
+        assert!(issues.is_empty(), "unexpected issues: {:?}", issues);
+    }
+
+    #[test]
+    fn test_lint_duplicate_and_overlap() {
+        let mut config = default_config();
+        config.auto_approve.bash_patterns.push(r"^ls(\s|$)".into());
+        config.auto_approve.bash_patterns.push(r"^git\s+(reset|checkout)".into());
+
+        let issues = lint_config(&config);
+
+        let dup = issues.iter().find(|i| i.kind == LintKind::Duplicate).expect("duplicate reported");
+        assert!(dup.message.contains(r"^ls(\s|$)"));
+
+        let overlap = issues.iter().find(|i| i.kind == LintKind::Overlap).expect("overlap reported");
+        assert!(overlap.field.starts_with("auto_deny.bash_patterns"));
+        assert!(overlap.message.contains("git reset --hard"));
+    }
+
+    #[test]
+    fn test_lint_webhook_preset() {
+        let mut config = default_config();
+        config.notifications.webhook.preset = "slack".into();
+        config.notifications.webhook.url = "https://discord.com/api/webhooks/1/abc".into();
+
+        let issues = lint_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, LintKind::WebhookPreset);
+    }
+
+    #[test]
+    fn test_lint_invalid_regex() {
+        let mut config = default_config();
+        config.auto_deny.protected_paths.push("([".into());
+
+        let issues = lint_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, LintKind::InvalidRegex);
+        assert!(issues[0].field.starts_with("auto_deny.protected_paths["));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        let mut config = default_config();
+        assert!(invalid_patterns(&config).is_empty());
+        let count = pattern_count(&config);
+
+        config.auto_approve.bash_patterns.push("^git (status".into());
+        config.inline_scripts.dangerous_node_patterns.push("eval(".into());
+        config.inline_scripts.dangerous_node_patterns.push("eval(".into());
+        assert_eq!(pattern_count(&config), count + 3);
+
+        let issues = invalid_patterns(&config);
+        let fields: Vec<&str> = issues.iter().map(|i| i.field.as_str()).collect();
+        let node = config.inline_scripts.dangerous_node_patterns.len();
+        assert_eq!(
+            fields,
+            vec![
+                format!("auto_approve.bash_patterns[{}]", config.auto_approve.bash_patterns.len() - 1),
+                format!("inline_scripts.dangerous_node_patterns[{}]", node - 2),
+                format!("inline_scripts.dangerous_node_patterns[{}]", node - 1),
+            ]
+        );
+        assert!(issues[0].message.ends_with("does not compile: unclosed group"), "{}", issues[0].message);
+    }
+
+    #[test]
+    fn test_regex_error_summary() {
+        let syntax = patterns::compile("^git (status").unwrap_err();
+        assert_eq!(regex_error_summary(&syntax), "unclosed group");
+        let too_big = regex_error_summary(&patterns::compile(r"\w{1000}{1000}").unwrap_err());
+        assert!(too_big.contains("size limit") && !too_big.contains('\n'), "{}", too_big);
+        assert_eq!(first_line("one\ntwo"), "one");
+    }
+
+    #[test]
+    fn test_lint_invalid_hash() {
+        let mut config = default_config();
+        config.auto_approve.approved_command_hashes = vec![crate::permission::command_hash("make clean"), "rm -rf build".into()];
+
+        let issues = lint_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, LintKind::InvalidHash);
+        assert_eq!(issues[0].field, "auto_approve.approved_command_hashes[1]");
+    }
+}
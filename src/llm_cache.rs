@@ -0,0 +1,152 @@
+//! On-disk cache of LLM verdicts (`ambiguous.llm.cache_ttl_seconds`)
+//!
+//! Claude often retries the same ambiguous command; asking the LLM again costs
+//! money and latency for the same answer. Verdicts are stored in one JSON file
+//! in the config dir (a protected path), keyed by a hash of the request and
+//! the model asked:
+//!
+//! `{"<key>": {"decision": "allow" | "deny", "reason": "...", "created": 1700000000}}`
+//!
+//! Only SAFE/DANGEROUS answers are cached; errors and unclear answers are asked
+//! again next time. Expired entries are dropped whenever the file is written.
+
+use crate::config::LlmConfig;
+use crate::permission::LlmOutcome;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Entry {
+    decision: String,
+    reason: String,
+    created: i64,
+}
+
+type Cache = BTreeMap<String, Entry>;
+
+/// Key for a request: SHA-256 of the tool name and input, the cwd when it is
+/// sent to the model (`include_context`), and the model and endpoint asked.
+/// serde_json keeps object keys sorted, so the same input always serializes
+/// the same way.
+pub fn cache_key(llm: &LlmConfig, tool_name: &str, tool_input: &serde_json::Value, cwd: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let cwd = if llm.include_context { cwd } else { "" };
+    let request = serde_json::json!([tool_name, tool_input, cwd, llm.model, llm.base_url]);
+    Sha256::digest(request.to_string().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn read_cache(path: &Path) -> Cache {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn is_fresh(entry: &Entry, ttl_seconds: u64, now: i64) -> bool {
+    now - entry.created < ttl_seconds as i64
+}
+
+/// The cached verdict for `key`, if there is one younger than `ttl_seconds`
+pub fn lookup(path: &Path, key: &str, ttl_seconds: u64, now: i64) -> Option<LlmOutcome> {
+    let cache = read_cache(path);
+    let entry = cache.get(key).filter(|entry| is_fresh(entry, ttl_seconds, now))?;
+    match entry.decision.as_str() {
+        "allow" => Some(LlmOutcome::Allowed(entry.reason.clone())),
+        "deny" => Some(LlmOutcome::Denied(entry.reason.clone())),
+        _ => None,
+    }
+}
+
+/// Remember a SAFE/DANGEROUS verdict for `key`; other outcomes aren't cached
+pub fn store(path: &Path, key: &str, outcome: &LlmOutcome, ttl_seconds: u64, now: i64) -> Result<(), String> {
+    let (decision, reason) = match outcome {
+        LlmOutcome::Allowed(reason) => ("allow", reason),
+        LlmOutcome::Denied(reason) => ("deny", reason),
+        _ => return Ok(()),
+    };
+
+    // Read-modify-write, so hold the lock throughout
+    let _lock = crate::logging::FileLock::acquire(path);
+    let mut cache = read_cache(path);
+    cache.retain(|_, entry| is_fresh(entry, ttl_seconds, now));
+    cache.insert(key.to_string(), Entry { decision: decision.into(), reason: reason.clone(), created: now });
+
+    let content = serde_json::to_string(&cache).map_err(|e| e.to_string())?;
+    // Written through a temp file of its own, so a concurrent hook never reads half a file
+    crate::platform::write_private_file(path, &content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("llm_cache.json");
+        let llm = LlmConfig::default();
+        let key = cache_key(&llm, "Bash", &serde_json::json!({"command": "make deploy"}), "/repo");
+        let allowed = LlmOutcome::Allowed("LLM determined operation is safe 'builds'".into());
+
+        assert_eq!(lookup(&path, &key, 3600, 1000), None);
+        store(&path, &key, &allowed, 3600, 1000).unwrap();
+        assert_eq!(lookup(&path, &key, 3600, 1000), Some(allowed));
+
+        // A different input is a different entry
+        let other = cache_key(&llm, "Bash", &serde_json::json!({"command": "make deploy-prod"}), "/repo");
+        assert_eq!(lookup(&path, &other, 3600, 1000), None);
+        assert_ne!(key, cache_key(&llm, "Read", &serde_json::json!({"command": "make deploy"}), "/repo"));
+
+        let denied = LlmOutcome::Denied("LLM determined operation is dangerous".into());
+        store(&path, &other, &denied, 3600, 1000).unwrap();
+        assert_eq!(lookup(&path, &other, 3600, 1000), Some(denied));
+    }
+
+    #[test]
+    fn test_cache_key_covers_model_and_context() {
+        let mut llm = LlmConfig::default();
+        let input = serde_json::json!({"command": "make deploy"});
+        let key = cache_key(&llm, "Bash", &input, "/repo");
+        assert_eq!(key.len(), 64);
+
+        // The cwd only counts when it is sent to the model
+        assert_eq!(key, cache_key(&llm, "Bash", &input, "/other"));
+        llm.include_context = true;
+        assert_ne!(cache_key(&llm, "Bash", &input, "/repo"), cache_key(&llm, "Bash", &input, "/other"));
+        llm.include_context = false;
+
+        llm.model = "other-model".into();
+        assert_ne!(key, cache_key(&llm, "Bash", &input, "/repo"));
+        llm.model = LlmConfig::default().model;
+        llm.base_url = "https://llm.example/v1".into();
+        assert_ne!(key, cache_key(&llm, "Bash", &input, "/repo"));
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("llm_cache.json");
+        let allowed = LlmOutcome::Allowed("LLM determined operation is safe".into());
+        store(&path, "old", &allowed, 60, 1000).unwrap();
+
+        assert!(lookup(&path, "old", 60, 1059).is_some());
+        assert_eq!(lookup(&path, "old", 60, 1060), None);
+
+        // Expired entries are pruned on the next write
+        store(&path, "new", &allowed, 60, 1100).unwrap();
+        assert_eq!(read_cache(&path).keys().collect::<Vec<_>>(), vec!["new"]);
+    }
+
+    #[test]
+    fn test_only_verdicts_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("llm_cache.json");
+        for outcome in [LlmOutcome::Unsure, LlmOutcome::Error("timed out".into()), LlmOutcome::NotConfigured] {
+            store(&path, "key", &outcome, 3600, 1000).unwrap();
+            assert_eq!(lookup(&path, "key", 3600, 1000), None);
+        }
+        assert!(!path.exists());
+    }
+}
@@ -1,11 +1,68 @@
 //! Logging utilities for permission-hook
 
-use crate::config::{get_config_dir, get_log_path, get_prompts_path, Config};
+use crate::config::{get_config_dir, get_inputs_log_path, get_log_path, get_project_log_path, get_prompts_path, Config};
+use crate::platform;
 use chrono::Utc;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-const CSV_HEADER: &str = "timestamp,tool,decision,reason,details";
+pub(crate) const CSV_HEADER: &str = "timestamp,tool,decision,reason,details";
+
+/// Longest string value kept when logging a full tool_input
+const MAX_INPUT_STRING_LEN: usize = 1000;
+
+/// Object keys whose values are never logged
+const SECRET_KEY_PARTS: &[&str] = &[
+    "password", "passwd", "secret", "token", "api_key", "apikey", "authorization", "credential", "private_key",
+];
+
+/// How long to wait for another hook process to release a file lock
+const LOCK_WAIT: Duration = Duration::from_secs(1);
+/// Locks older than this are assumed to belong to a crashed process
+const LOCK_STALE_SECONDS: i64 = 5;
+
+/// Lock file held while a shared state file is being modified; removed on drop
+pub(crate) struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Acquire `<file>.lock`, waiting briefly for other processes.
+    /// Returns None if the lock can't be acquired in time.
+    pub(crate) fn acquire(file: &Path) -> Option<Self> {
+        let mut name = file.as_os_str().to_os_string();
+        name.push(".lock");
+        let path = PathBuf::from(name);
+        let started = Instant::now();
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Some(FileLock { path }),
+                Err(_) => {
+                    let stale = platform::file_mtime(path.to_str().unwrap_or(""))
+                        .map(|mtime| platform::current_timestamp() - mtime > LOCK_STALE_SECONDS)
+                        .unwrap_or(false);
+                    if stale {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed() >= LOCK_WAIT {
+                        return None;
+                    }
+                    std::thread::sleep(Duration::from_millis(2));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
 
 /// Truncate string to max length (UTF-8 safe)
 pub fn truncate(s: &str, max_len: usize) -> String {
@@ -21,8 +78,13 @@ pub fn truncate(s: &str, max_len: usize) -> String {
     }
 }
 
+/// Escape line breaks as `\n`/`\r`, so a value can't span or forge log lines
+pub(crate) fn escape_newlines(s: &str) -> String {
+    s.replace('\r', "\\r").replace('\n', "\\n")
+}
+
 /// Escape CSV field (wrap in quotes if contains comma, quote, or newline)
-fn escape_csv(s: &str) -> String {
+pub(crate) fn escape_csv(s: &str) -> String {
     if s.contains(',') || s.contains('"') || s.contains('\n') {
         format!("\"{}\"", s.replace('"', "\"\""))
     } else {
@@ -40,41 +102,134 @@ fn decision_code(decision: &str) -> &str {
     }
 }
 
-/// Log a permission decision
-pub fn log_decision(config: &Config, tool: &str, decision: &str, reason: &str, details: Option<&str>) {
+/// Log a permission decision for a request made in `cwd`
+pub fn log_decision(config: &Config, tool: &str, decision: &str, reason: &str, details: Option<&str>, cwd: &str) {
     if !config.logging.enabled {
         return;
     }
 
-    let log_dir = get_config_dir();
-    let _ = fs::create_dir_all(&log_dir);
+    let log_path = decision_log_path(config, cwd);
+    if let Some(log_dir) = log_path.parent() {
+        let _ = fs::create_dir_all(log_dir);
+    }
+
+    let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let line = decision_line(config, &timestamp, tool, decision, reason, details);
+    append_log_line(&log_path, &line);
+}
+
+/// Where a decision made in `cwd` is logged: the project's own log with
+/// `logging.per_project`, otherwise (or when cwd is unknown) decisions.log
+pub fn decision_log_path(config: &Config, cwd: &str) -> PathBuf {
+    config
+        .logging
+        .per_project
+        .then(|| get_project_log_path(cwd))
+        .flatten()
+        .unwrap_or_else(get_log_path)
+}
+
+/// Format a decision log line: timestamp,tool,decision,reason,details.
+/// Reason and details are cut to `logging.max_reason_len`/`max_detail_len`.
+fn decision_line(config: &Config, timestamp: &str, tool: &str, decision: &str, reason: &str, details: Option<&str>) -> String {
+    let limit = |s: &str, max_len: usize| {
+        let s = escape_newlines(s);
+        if max_len == 0 { s } else { truncate(&s, max_len) }
+    };
+    format!(
+        "{},{},{},{},{}",
+        timestamp,
+        tool,
+        decision_code(decision),
+        escape_csv(&limit(reason, config.logging.max_reason_len)),
+        escape_csv(&limit(details.unwrap_or("-"), config.logging.max_detail_len))
+    )
+}
 
-    let log_path = get_log_path();
+/// Log a request's full tool_input as a JSON line (`logging.log_full_input`)
+pub fn log_full_input(config: &Config, tool: &str, decision: &str, input: &serde_json::Value) {
+    if !config.logging.enabled || !config.logging.log_full_input {
+        return;
+    }
+    let _ = fs::create_dir_all(get_config_dir());
 
-    // Check if file is empty/new to write header
-    let needs_header = !log_path.exists() || fs::metadata(&log_path).map(|m| m.len() == 0).unwrap_or(true);
+    let entry = serde_json::json!({
+        "timestamp": Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "tool": tool,
+        "decision": decision,
+        "tool_input": redact_input(input),
+    });
+    append_line(&get_inputs_log_path(), None, &entry.to_string());
+}
+
+/// Copy of a tool_input with secret-looking values replaced and long strings truncated
+pub fn redact_input(value: &serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, v)| {
+                    let key_lower = key.to_lowercase();
+                    if SECRET_KEY_PARTS.iter().any(|part| key_lower.contains(part)) {
+                        (key.clone(), Value::String("[REDACTED]".into()))
+                    } else {
+                        (key.clone(), redact_input(v))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_input).collect()),
+        Value::String(s) => Value::String(truncate(&redact_inline_secrets(s), MAX_INPUT_STRING_LEN)),
+        other => other.clone(),
+    }
+}
+
+/// Redact secrets inside strings, e.g. `Authorization: Bearer x` or `--password=x`
+fn redact_inline_secrets(s: &str) -> String {
+    use regex::Regex;
+    use std::sync::OnceLock;
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            r"(?i)(authorization:\s*(?:bearer|basic|token)\s+)[^\s'\x22]+",
+            r"(?i)((?:password|passwd|token|secret|api[_-]?key)[=:]\s*)[^\s&'\x22]+",
+        ]
+        .iter()
+        .filter_map(|p| Regex::new(p).ok())
+        .collect()
+    });
+    patterns
+        .iter()
+        .fold(s.to_string(), |acc, re| re.replace_all(&acc, "${1}[REDACTED]").into_owned())
+}
+
+/// Append a line to a CSV log, writing the header first if the file is new.
+/// The header check and write happen under a lock so concurrent hook
+/// processes can't duplicate or drop it.
+fn append_log_line(log_path: &Path, line: &str) {
+    append_line(log_path, Some(CSV_HEADER), line);
+}
+
+/// Append a line under the file's lock, writing `header` first if the file is new
+fn append_line(log_path: &Path, header: Option<&str>, line: &str) {
+    let _lock = FileLock::acquire(log_path);
+
+    let needs_header = fs::metadata(log_path).map(|m| m.len() == 0).unwrap_or(true);
 
     if let Ok(mut file) = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&log_path)
+        .open(log_path)
     {
-        // Write header if new file
-        if needs_header {
-            let _ = writeln!(file, "{}", CSV_HEADER);
+        // Single write so lines never interleave
+        let mut buf = String::new();
+        if let Some(header) = header.filter(|_| needs_header) {
+            buf.push_str(header);
+            buf.push('\n');
         }
-
-        // Format: timestamp,tool,decision,reason,details
-        let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string();
-        let line = format!(
-            "{},{},{},{},{}",
-            timestamp,
-            tool,
-            decision_code(decision),
-            escape_csv(&truncate(reason, 150)),
-            escape_csv(&truncate(details.unwrap_or("-"), 100))
-        );
-        let _ = writeln!(file, "{}", line);
+        buf.push_str(line);
+        buf.push('\n');
+        let _ = file.write_all(buf.as_bytes());
     }
 }
 
@@ -84,6 +239,9 @@ pub fn log_prompt(tool: &str, details: Option<&str>) {
     let log_dir = get_config_dir();
     let _ = fs::create_dir_all(&log_dir);
 
+    // Read-modify-write, so hold the lock throughout
+    let _lock = FileLock::acquire(&prompts_path);
+
     // Read existing prompts, keep only last 50 lines
     let existing: Vec<String> = fs::read_to_string(&prompts_path)
         .unwrap_or_default()
@@ -96,32 +254,210 @@ pub fn log_prompt(tool: &str, details: Option<&str>) {
 
     // Add new prompt
     let timestamp = Utc::now().format("%H:%M:%S").to_string();
-    let detail_str = details.unwrap_or("-");
+    let detail_str = escape_newlines(details.unwrap_or("-"));
     lines.push(format!("{} | {} | {}", timestamp, tool, detail_str));
 
     // Write back
     let _ = fs::write(&prompts_path, lines.join("\n") + "\n");
 }
 
+/// Category of a stderr message, filtered by `logging.stderr_level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StderrKind {
+    /// Allow/prompt decision lines (verbose-only at level "all")
+    Decision,
+    /// The deny banner
+    Deny,
+    Warning,
+    Debug,
+}
+
+/// Whether a message of this kind goes to stderr
+///
+/// - silent: nothing
+/// - decisions: allow/deny/prompt lines
+/// - warn: warnings only
+/// - all: deny banner and warnings, plus everything else when verbose
+pub fn stderr_enabled(config: &Config, kind: StderrKind) -> bool {
+    match config.logging.stderr_level.as_str() {
+        "silent" => false,
+        "decisions" => matches!(kind, StderrKind::Decision | StderrKind::Deny),
+        "warn" => kind == StderrKind::Warning,
+        _ => match kind {
+            StderrKind::Deny | StderrKind::Warning => true,
+            StderrKind::Decision | StderrKind::Debug => config.logging.verbose,
+        },
+    }
+}
+
+/// Write a stderr-style message to `out` if the configured level allows it
+pub fn emit(out: &mut dyn Write, config: &Config, kind: StderrKind, message: &str) {
+    if stderr_enabled(config, kind) {
+        let _ = writeln!(out, "{}", message);
+    }
+}
+
 /// Debug logging (only when verbose is enabled)
 pub fn debug(config: &Config, message: &str) {
-    if config.logging.verbose {
-        eprintln!("[permission-hook] {}", message);
-    }
+    emit(&mut std::io::stderr(), config, StderrKind::Debug, &format!("[permission-hook] {}", message));
 }
 
-/// Warning logging (always shown)
-pub fn warn(message: &str) {
-    eprintln!("[permission-hook] WARN: {}", message);
+/// Warning logging
+pub fn warn(config: &Config, message: &str) {
+    emit(&mut std::io::stderr(), config, StderrKind::Warning, &format!("[permission-hook] WARN: {}", message));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn config_with_level(level: &str, verbose: bool) -> Config {
+        let mut config = Config::default();
+        config.logging.stderr_level = level.into();
+        config.logging.verbose = verbose;
+        config
+    }
+
+    #[test]
+    fn test_silent_allow_emits_nothing() {
+        let config = config_with_level("silent", true);
+        let mut out = Vec::new();
+        emit(&mut out, &config, StderrKind::Decision, "[permission-hook] ALLOW: Bash - git status");
+        emit(&mut out, &config, StderrKind::Deny, "[permission-hook] DENY: Bash - dangerous pattern");
+        emit(&mut out, &config, StderrKind::Warning, "[permission-hook] WARN: oops");
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_stderr_levels() {
+        let decisions = config_with_level("decisions", false);
+        assert!(stderr_enabled(&decisions, StderrKind::Decision));
+        assert!(stderr_enabled(&decisions, StderrKind::Deny));
+        assert!(!stderr_enabled(&decisions, StderrKind::Warning));
+
+        let warn = config_with_level("warn", true);
+        assert!(stderr_enabled(&warn, StderrKind::Warning));
+        assert!(!stderr_enabled(&warn, StderrKind::Decision));
+        assert!(!stderr_enabled(&warn, StderrKind::Debug));
+
+        // "all" keeps the previous behavior: allow lines and debug only when verbose
+        let all = config_with_level("all", false);
+        assert!(stderr_enabled(&all, StderrKind::Deny));
+        assert!(stderr_enabled(&all, StderrKind::Warning));
+        assert!(!stderr_enabled(&all, StderrKind::Decision));
+        assert!(stderr_enabled(&config_with_level("all", true), StderrKind::Decision));
+
+        let mut out = Vec::new();
+        emit(&mut out, &decisions, StderrKind::Decision, "ALLOW: Bash - ls");
+        assert_eq!(String::from_utf8(out).unwrap(), "ALLOW: Bash - ls\n");
+    }
+
+    #[test]
+    fn test_concurrent_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("decisions.log");
+
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for i in 0..50 {
+                        append_log_line(&path, &format!("2024-01-01T00:00:00,Bash,Y,thread {} line {},-", t, i));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.iter().filter(|l| **l == CSV_HEADER).count(), 1);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), 1 + 8 * 50);
+        assert!(lines[1..].iter().all(|l| l.starts_with("2024-01-01T00:00:00,Bash,Y,thread ") && l.ends_with(",-")));
+        assert!(!dir.path().join("decisions.log.lock").exists());
+    }
+
     #[test]
     fn test_truncate() {
         assert_eq!(truncate("hello", 10), "hello");
         assert_eq!(truncate("hello world", 5), "hello...");
     }
+
+    #[test]
+    fn test_redact_input() {
+        let input = serde_json::json!({
+            "command": "curl -H 'Authorization: Bearer sk-live-123' https://api.example.com?api_key=abc123&q=1",
+            "env": {"API_KEY": "abc", "GITHUB_TOKEN": "ghp_x", "PATH": "/usr/bin"},
+            "password": "hunter2",
+            "edits": [{"new_string": "x".repeat(2000)}],
+            "timeout": 5
+        });
+        let redacted = redact_input(&input);
+
+        assert_eq!(redacted["password"], "[REDACTED]");
+        assert_eq!(redacted["env"]["API_KEY"], "[REDACTED]");
+        assert_eq!(redacted["env"]["GITHUB_TOKEN"], "[REDACTED]");
+        assert_eq!(redacted["env"]["PATH"], "/usr/bin");
+        assert_eq!(redacted["timeout"], 5);
+
+        let command = redacted["command"].as_str().unwrap();
+        assert!(!command.contains("sk-live-123") && !command.contains("abc123"), "{}", command);
+        assert!(command.contains("Authorization: Bearer [REDACTED]"));
+        assert!(command.contains("&q=1"));
+
+        assert_eq!(redacted["edits"][0]["new_string"].as_str().unwrap().len(), MAX_INPUT_STRING_LEN + 3);
+    }
+
+    #[test]
+    fn test_decision_line_truncation() {
+        let mut config = Config::default();
+        let reason = "é".repeat(200);
+        let details = "x".repeat(300);
+
+        // Defaults: 150 bytes of reason, 100 of details (UTF-8 safe)
+        let line = decision_line(&config, "T", "Bash", "deny", &reason, Some(&details));
+        let fields: Vec<&str> = line.split(',').collect();
+        assert_eq!(fields[2], "N");
+        assert_eq!(fields[3], format!("{}...", "é".repeat(75)));
+        assert_eq!(fields[4], format!("{}...", "x".repeat(100)));
+
+        config.logging.max_reason_len = 1000;
+        config.logging.max_detail_len = 0;
+        let line = decision_line(&config, "T", "Bash", "deny", &reason, Some(&details));
+        assert_eq!(line, format!("T,Bash,N,{},{}", reason, details));
+    }
+
+    #[test]
+    fn test_decision_line_escapes_newlines() {
+        let config = Config::default();
+        let line = decision_line(&config, "T", "Bash", "deny", "dangerous pattern", Some("echo hi\nrm -rf /\r"));
+        assert_eq!(line, "T,Bash,N,dangerous pattern,echo hi\\nrm -rf /\\r");
+        assert_eq!(line.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_decision_log_path_per_project() {
+        let mut config = Config::default();
+        assert_eq!(decision_log_path(&config, "/home/dev/app"), get_log_path());
+
+        config.logging.per_project = true;
+        let app = decision_log_path(&config, "/home/dev/app");
+        let api = decision_log_path(&config, "/home/dev/api");
+        assert_ne!(app, api);
+        assert_ne!(app, get_log_path());
+        assert_eq!(app, decision_log_path(&config, "/home/dev/app"));
+        let name = app.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("app-") && name.ends_with(".log"), "{}", name);
+        assert_eq!(app.parent().unwrap().file_name().unwrap(), "projects");
+        // Paths that flatten to the same name still get their own log
+        assert_ne!(decision_log_path(&config, "/home/dev/my app"), decision_log_path(&config, "/home/dev/my-app"));
+        assert_ne!(decision_log_path(&config, "/a/b/app"), app);
+
+        // Unknown cwd falls back to the global log
+        assert_eq!(decision_log_path(&config, ""), get_log_path());
+        assert_eq!(decision_log_path(&config, "/"), get_log_path());
+    }
 }
@@ -6,33 +6,26 @@
 //! - SubagentStop: Subagent completion notifications
 //! - Notification: Permission prompt notifications
 
-mod config;
-mod permission;
-mod logging;
-mod jsonl;
-mod analyzer;
-mod state;
-mod dedup;
-mod platform;
-mod summary;
-mod notifier;
-mod audio;
-mod webhook;
-mod update;
-
-use config::{load_config, Config};
-use permission::{HookInput, HookResponse, is_auto_approved, is_auto_denied, ask_llm, extract_details};
-use logging::{log_decision, log_prompt, debug};
-use analyzer::{analyze_transcript, get_status_for_pre_tool_use, Status};
-use state::Manager as StateManager;
-use dedup::Manager as DedupManager;
-use notifier::{send_notification, send_alert_notification, should_notify};
-use summary::{generate_summary, generate_session_name};
-use audio::{play_sound, play_alert_sound};
-use webhook::{send_webhook, should_send_webhook, CircuitBreaker, RateLimiter};
-use update::{check_for_update, mark_notified};
-
-use std::io::{self, BufRead};
+use claude_permission_hook::{anomaly, doctor, explain, export, grant, jsonl, logging, notifier, patterns, platform, remember, update, watch, webhook};
+use claude_permission_hook::config::{
+    default_config, find_config_path, find_project_config, get_log_path, get_project_log_path, get_trusted_projects_path, load_config, load_config_file,
+    load_project_config, trust_project_config, Config, PROJECT_CONFIG_FILE,
+};
+use claude_permission_hook::permission::{command_hash, decision_summary, evaluate, extract_description, extract_details, extract_log_details, Decision, HookInput, LLM_CONTEXT_DECISIONS};
+use claude_permission_hook::logging::{log_decision, log_prompt, debug};
+use claude_permission_hook::output::render_decision;
+use claude_permission_hook::analyzer::{analyze_transcript, get_status_for_pre_tool_use, Status};
+use claude_permission_hook::state::Manager as StateManager;
+use claude_permission_hook::dedup::Manager as DedupManager;
+use claude_permission_hook::notifier::{run_on_deny_command, send_notification, send_alert_notification, should_notify};
+use claude_permission_hook::summary::{generate_summary, generate_session_name, grouped_question_summary};
+use claude_permission_hook::audio::{play_sound, play_alert_sound};
+use claude_permission_hook::webhook::{send_webhook, send_webhook_once, should_send_webhook, CircuitBreaker, RateLimiter};
+use claude_permission_hook::update::{check_for_update, mark_notified, VERSION};
+use claude_permission_hook::lint::{invalid_patterns, lint_config, pattern_count};
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
 /// Handle PreToolUse hook event (permission decisions)
 fn handle_pre_tool_use(config: &Config, input: &HookInput, state_mgr: &StateManager) {
@@ -46,76 +39,173 @@ fn handle_pre_tool_use(config: &Config, input: &HookInput, state_mgr: &StateMana
     let tool_input = input.get_tool_input();
     let details = extract_details(&tool_input);
     let details_ref = details.as_deref();
+    // The log also gets Bash's description of the command
+    let log_details = extract_log_details(&tool_input);
+    let log_details_ref = log_details.as_deref();
+    let description = extract_description(&tool_input);
+
+    let decision = evaluate(config, input);
+    // Bad regexes are skipped when matching; say so instead of silently ignoring them
+    patterns::warn_invalid(config);
+    let decision_name = match &decision {
+        Decision::Allow { .. } => "allow",
+        Decision::Deny { .. } => "deny",
+        Decision::Prompt { .. } => "prompt",
+    };
+    logging::log_full_input(config, &tool_name, decision_name, &tool_input);
+    if config.ambiguous.llm.include_context {
+        let summary = decision_summary(&tool_name, &tool_input, &decision);
+        if let Err(e) = state_mgr.record_recent_decision(&input.get_session_id(), &summary, LLM_CONTEXT_DECISIONS) {
+            logging::warn(config, &format!("Failed to record decision: {}", e));
+        }
+    }
+    if config.ambiguous.warmup_prompts > 0 {
+        if let Err(e) = state_mgr.record_decision(&input.get_session_id()) {
+            logging::warn(config, &format!("Failed to record decision: {}", e));
+        }
+    }
+    send_heartbeat(config, input, state_mgr);
+    record_session_history(config, input, &tool_name, &tool_input, &decision);
+    match &decision {
+        Decision::Allow { reason } => {
+            log_decision(config, &tool_name, "allow", reason, log_details_ref, &input.get_cwd());
+            // A hashed approval overriding a deny should never go unnoticed
+            if reason.starts_with("approved command hash") && reason.contains(" overriding ") {
+                logging::warn(config, &format!("ALLOWED DESPITE DENY RULE: {}", reason));
+            }
+        }
+        Decision::Deny { reason, segment } => {
+            log_decision(config, &tool_name, "deny", reason, log_details_ref, &input.get_cwd());
+            if let Err(e) = run_on_deny_command(config, &tool_name, reason, details_ref) {
+                logging::warn(config, &e);
+            }
+
+            // Send alert notification and sound
+            if config.features.notifications {
+                let _ = send_alert_notification(
+                    config,
+                    &tool_name,
+                    reason,
+                    details_ref,
+                    segment.as_deref(),
+                    description.as_deref(),
+                );
+                let _ = play_alert_sound(config);
+            }
+        }
+        Decision::Prompt { reason } => {
+            // Check for interactive tools (ExitPlanMode, AskUserQuestion)
+            let status = get_status_for_pre_tool_use(&tool_name);
+            if status != Status::Unknown {
+                // Update state for interactive tools
+                let session_id = input.get_session_id();
+                let cwd = input.get_cwd();
+                if let Err(e) = state_mgr.update_interactive_tool(&session_id, &tool_name, &cwd) {
+                    logging::warn(config, &format!("Failed to update interactive tool state: {}", e));
+                }
+                debug(config, &format!("Interactive tool: {} -> {:?}", tool_name, status));
+            }
 
-    // Tier 1: Check auto-approve
-    if let Some(reason) = is_auto_approved(config, &tool_name, &tool_input) {
-        log_decision(config, &tool_name, "allow", &reason, details_ref);
-        debug(config, &format!("ALLOW: {} - {}", tool_name, reason));
+            // Remembered so `remember` can approve it for the rest of the session
+            if let Some(command) = tool_input.get("command").and_then(|c| c.as_str()).filter(|_| tool_name == "Bash") {
+                let recorded = remember::record_prompted(&remember::remembered_path(), &input.get_session_id(), command.trim(), platform::current_timestamp());
+                if let Err(e) = recorded {
+                    logging::warn(config, &format!("Failed to record prompted command: {}", e));
+                }
+            }
 
-        // Output JSON to actually allow the command
-        let response = HookResponse::allow(&reason);
-        println!("{}", serde_json::to_string(&response).unwrap());
-        std::process::exit(0);
+            // Fall through to Claude's default behavior (prompt user)
+            log_decision(config, &tool_name, "prompt", reason, log_details_ref, &input.get_cwd());
+            log_prompt(&tool_name, log_details_ref);
+        }
     }
 
-    // Tier 2: Check auto-deny
-    if let Some(reason) = is_auto_denied(config, &tool_name, &tool_input) {
-        log_decision(config, &tool_name, "deny", &reason, details_ref);
+    // Allow prints JSON, deny exits 2, prompt exits 0 with no output
+    // (passthrough to Claude's native permissions)
+    let code = render_decision(
+        config,
+        &tool_name,
+        details_ref,
+        &decision,
+        &mut io::stdout(),
+        &mut io::stderr(),
+    );
+    // Watch notifications go out after the decision, so a slow endpoint can't hold it up
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    notify_watched_command(config, input, &tool_name, &tool_input, &decision);
+    std::process::exit(code);
+}
 
-        // Send alert notification and sound
-        if config.features.notifications {
-            let _ = send_alert_notification(config, &tool_name, &reason, details_ref);
-            let _ = play_alert_sound(config);
-        }
+/// Add the request to the session's history for the anomaly check. Denied
+/// requests never run, so they don't count; an anomaly prompt does, since the
+/// user has now seen the session change course.
+fn record_session_history(
+    config: &Config,
+    input: &HookInput,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+    decision: &Decision,
+) {
+    if !config.anomaly.enabled || matches!(decision, Decision::Deny { .. }) {
+        return;
+    }
+    let class = anomaly::classify(tool_name, tool_input);
+    anomaly::record(&anomaly::history_path(&input.get_session_id()), class);
+}
 
-        eprintln!("[permission-hook] DENY: {} - {}", tool_name, reason);
-        std::process::exit(2);
+/// Let the user know a long session is still going (`notifications.heartbeat_seconds`)
+fn send_heartbeat(config: &Config, input: &HookInput, state_mgr: &StateManager) {
+    let interval = config.notifications.heartbeat_seconds;
+    if !config.features.notifications || interval <= 0 {
+        return;
+    }
+    let session_id = input.get_session_id();
+    match state_mgr.record_activity(&session_id, interval) {
+        Ok(true) => {
+            let cwd = input.get_cwd();
+            let git_branch = platform::get_git_branch(&cwd);
+            let session_name = generate_session_name(&session_id, &cwd, git_branch.as_deref());
+            if let Err(e) = notifier::send_heartbeat_notification(config, &session_name) {
+                logging::warn(config, &e);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => logging::warn(config, &format!("Failed to record activity: {}", e)),
     }
+}
 
-    // Trust mode: auto-approve everything that wasn't denied
-    if config.features.trust_mode {
-        let reason = "trust mode enabled";
-        log_decision(config, &tool_name, "allow", reason, details_ref);
-        debug(config, &format!("ALLOW (trust mode): {} - {}", tool_name, details_ref.unwrap_or("no details")));
+/// How long a watched-command webhook may take; it's sent once, never retried
+const WATCH_WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
 
-        let response = HookResponse::allow(reason);
-        println!("{}", serde_json::to_string(&response).unwrap());
-        std::process::exit(0);
+/// Notify about commands matching `notifications.watch_patterns`, whatever the decision
+fn notify_watched_command(
+    config: &Config,
+    input: &HookInput,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+    decision: &Decision,
+) {
+    if !config.features.notifications {
+        return;
     }
+    let Some(message) = notifier::watch_message(config, tool_name, tool_input, decision) else {
+        return;
+    };
+    debug(config, &message);
 
-    // Tier 3: Ambiguous - use LLM if configured, otherwise prompt user
-    if let Some((decision_type, reason)) = ask_llm(config, &tool_name, &tool_input) {
-        log_decision(config, &tool_name, &decision_type, &reason, details_ref);
-        if decision_type == "allow" {
-            let response = HookResponse::allow(&reason);
-            println!("{}", serde_json::to_string(&response).unwrap());
-            std::process::exit(0);
-        } else {
-            eprintln!("{}", reason);
-            std::process::exit(2);
-        }
+    if let Err(e) = notifier::send_watch_notification(config, &message) {
+        logging::warn(config, &e);
     }
 
-    // Check for interactive tools (ExitPlanMode, AskUserQuestion)
-    let status = get_status_for_pre_tool_use(&tool_name);
-    if status != Status::Unknown {
-        // Update state for interactive tools
-        let session_id = input.get_session_id();
+    if config.notifications.webhook.enabled {
         let cwd = input.get_cwd();
-        if let Err(e) = state_mgr.update_interactive_tool(&session_id, &tool_name, &cwd) {
-            logging::warn(&format!("Failed to update interactive tool state: {}", e));
+        let git_branch = platform::get_git_branch(&cwd);
+        let session_name = generate_session_name(&input.get_session_id(), &cwd, git_branch.as_deref());
+        if let Err(e) = send_webhook_once(config, Status::Unknown, &message, &session_name, WATCH_WEBHOOK_TIMEOUT) {
+            logging::warn(config, &format!("Webhook failed: {}", e));
         }
-        debug(config, &format!("Interactive tool: {} -> {:?}", tool_name, status));
     }
-
-    // Fall through to Claude's default behavior (prompt user)
-    let prompt_reason = format!("Prompting user for: {} ({})", tool_name, details_ref.unwrap_or("no details"));
-    log_decision(config, &tool_name, "prompt", &prompt_reason, details_ref);
-    log_prompt(&tool_name, details_ref);
-    debug(config, &prompt_reason);
-
-    // Exit 0 with no output = passthrough to Claude's native permissions
-    std::process::exit(0);
 }
 
 /// Handle Stop hook event (task completion)
@@ -153,7 +243,7 @@ fn handle_stop(
     let status = match analyze_transcript(transcript_path, config) {
         Ok(s) => s,
         Err(e) => {
-            logging::warn(&format!("Failed to analyze transcript: {}", e));
+            logging::warn(config, &format!("Failed to analyze transcript: {}", e));
             return;
         }
     };
@@ -163,6 +253,10 @@ fn handle_stop(
         return;
     }
 
+    if !claim_message(config, dedup_mgr, &session_id, transcript_path) {
+        return;
+    }
+
     // Phase 2: Acquire lock
     match dedup_mgr.acquire_lock(&session_id, Some("Stop")) {
         Ok(true) => {}
@@ -171,14 +265,14 @@ fn handle_stop(
             return;
         }
         Err(e) => {
-            logging::warn(&format!("Failed to acquire lock: {}", e));
+            logging::warn(config, &format!("Failed to acquire lock: {}", e));
             return;
         }
     }
 
     // Update state
     if let Err(e) = state_mgr.update_state(&session_id, status, "", &input.get_cwd()) {
-        logging::warn(&format!("Failed to update state: {}", e));
+        logging::warn(config, &format!("Failed to update state: {}", e));
     }
 
     // Log the status detection
@@ -191,6 +285,14 @@ fn handle_stop(
         Ok(messages) => generate_summary(&messages, status),
         Err(_) => String::new(),
     };
+    let summary = if status == Status::Question {
+        match group_questions(config, state_mgr, &session_id, &summary) {
+            Some(s) => s,
+            None => return,
+        }
+    } else {
+        summary
+    };
     let session_name = generate_session_name(&session_id, &cwd, git_branch.as_deref());
 
     // Send desktop notification if enabled
@@ -203,7 +305,7 @@ fn handle_stop(
             &cwd,
             git_branch.as_deref(),
         ) {
-            logging::warn(&format!("Failed to send notification: {}", e));
+            logging::warn(config, &format!("Failed to send notification: {}", e));
         } else {
             debug(config, &format!("Notification sent: {} - {}", status.as_str(), summary));
 
@@ -217,17 +319,18 @@ fn handle_stop(
     // Send webhook if enabled
     if should_send_webhook(config, status) {
         if let Err(e) = send_webhook(config, status, &summary, &session_name, circuit_breaker, rate_limiter) {
-            logging::warn(&format!("Webhook failed: {}", e));
+            logging::warn(config, &format!("Webhook failed: {}", e));
         } else {
             debug(config, "Webhook sent successfully");
         }
     }
 
-    log_decision(config, "Stop", "notify", status.as_str(), Some(&session_id));
+    log_decision(config, "Stop", "notify", status.as_str(), Some(&session_id), &input.get_cwd());
 
     // Cleanup old locks/state
     let _ = dedup_mgr.cleanup(60);
     let _ = state_mgr.cleanup(60);
+    let _ = state_mgr.cleanup_keep_newest(config.state.max_session_files);
 
     // Check for updates (non-blocking, cached)
     if let Some((current, latest)) = check_for_update(config) {
@@ -236,13 +339,55 @@ fn handle_stop(
 
         // Send update notification
         if let Err(e) = notifier::send_update_notification(config, &current, &latest) {
-            logging::warn(&format!("Failed to send update notification: {}", e));
+            logging::warn(config, &format!("Failed to send update notification: {}", e));
         } else {
             mark_notified();
         }
     }
 }
 
+/// Claim the notification for the transcript's last assistant message, so Stop
+/// and Notification don't both notify about the same turn. False if already
+/// notified; true when the transcript has no message id to key on.
+fn claim_message(config: &Config, dedup_mgr: &DedupManager, session_id: &str, transcript_path: &str) -> bool {
+    if transcript_path.is_empty() {
+        return true;
+    }
+    let Some(message_id) = jsonl::parse_transcript(transcript_path)
+        .ok()
+        .and_then(|messages| jsonl::last_assistant_message_id(&messages))
+    else {
+        return true;
+    };
+    match dedup_mgr.acquire_message_lock(session_id, &message_id) {
+        Ok(true) => true,
+        Ok(false) => {
+            debug(config, &format!("Already notified for message {}, skipping", message_id));
+            false
+        }
+        Err(e) => {
+            logging::warn(config, &format!("Failed to acquire message lock: {}", e));
+            true
+        }
+    }
+}
+
+/// Coalesce questions arriving in quick succession.
+/// Returns the summary to notify with, or None if this question was folded into a recent notification.
+fn group_questions(config: &Config, state_mgr: &StateManager, session_id: &str, summary: &str) -> Option<String> {
+    match state_mgr.record_question(session_id, config.notifications.question_group_window_seconds) {
+        Ok(Some(count)) => Some(grouped_question_summary(count, summary)),
+        Ok(None) => {
+            debug(config, "Question coalesced into recent notification");
+            None
+        }
+        Err(e) => {
+            logging::warn(config, &format!("Failed to record question: {}", e));
+            Some(summary.to_string())
+        }
+    }
+}
+
 /// Handle SubagentStop hook event
 fn handle_subagent_stop(
     config: &Config,
@@ -303,10 +448,14 @@ fn handle_notification(
         }
         Ok(false) => {}
         Err(e) => {
-            logging::warn(&format!("Failed to check cooldown: {}", e));
+            logging::warn(config, &format!("Failed to check cooldown: {}", e));
         }
     }
 
+    if !claim_message(config, dedup_mgr, &session_id, input.transcript_path.as_deref().unwrap_or("")) {
+        return;
+    }
+
     // Phase 2: Acquire lock
     match dedup_mgr.acquire_lock(&session_id, Some("Notification")) {
         Ok(true) => {}
@@ -315,7 +464,7 @@ fn handle_notification(
             return;
         }
         Err(e) => {
-            logging::warn(&format!("Failed to acquire lock: {}", e));
+            logging::warn(config, &format!("Failed to acquire lock: {}", e));
             return;
         }
     }
@@ -324,7 +473,7 @@ fn handle_notification(
 
     // Update state
     if let Err(e) = state_mgr.update_last_notification(&session_id, status, "Permission prompt") {
-        logging::warn(&format!("Failed to update notification state: {}", e));
+        logging::warn(config, &format!("Failed to update notification state: {}", e));
     }
 
     // Log the notification
@@ -333,7 +482,10 @@ fn handle_notification(
     // Generate session name for notifications
     let cwd = input.get_cwd();
     let git_branch = platform::get_git_branch(&cwd);
-    let summary = "Permission required";
+    let summary = match group_questions(config, state_mgr, &session_id, "Permission required") {
+        Some(s) => s,
+        None => return,
+    };
     let session_name = generate_session_name(&session_id, &cwd, git_branch.as_deref());
 
     // Send desktop notification if enabled
@@ -341,12 +493,12 @@ fn handle_notification(
         if let Err(e) = send_notification(
             config,
             status,
-            summary,
+            &summary,
             &session_id,
             &cwd,
             git_branch.as_deref(),
         ) {
-            logging::warn(&format!("Failed to send notification: {}", e));
+            logging::warn(config, &format!("Failed to send notification: {}", e));
         } else {
             debug(config, "Notification sent: question - Permission required");
 
@@ -359,18 +511,192 @@ fn handle_notification(
 
     // Send webhook if enabled
     if should_send_webhook(config, status) {
-        if let Err(e) = send_webhook(config, status, summary, &session_name, circuit_breaker, rate_limiter) {
-            logging::warn(&format!("Webhook failed: {}", e));
+        if let Err(e) = send_webhook(config, status, &summary, &session_name, circuit_breaker, rate_limiter) {
+            logging::warn(config, &format!("Webhook failed: {}", e));
         } else {
             debug(config, "Webhook sent successfully");
         }
     }
 
-    log_decision(config, "Notification", "notify", "question", Some(&session_id));
+    log_decision(config, "Notification", "notify", "question", Some(&session_id), &input.get_cwd());
+}
+
+/// Run a CLI subcommand if one was given.
+/// Returns the exit code, or None to run as a hook.
+fn run_subcommand(config: &Config, args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str)? {
+        "lint-config" => Some(run_lint_config(config)),
+        "watch" => Some(match project_log_arg(&args[1..]) {
+            Ok((path, rest)) if rest.is_empty() => watch::run_watch(path),
+            Ok(_) => {
+                eprintln!("Usage: claude-permission-hook watch [--project <dir>]");
+                1
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        }),
+        "grant" => Some(grant::run_grant(&args[1..])),
+        "preview-webhook" => Some(webhook::run_preview_webhook(config, &args[1..])),
+        "remember" => Some(remember::run_remember(&args[1..])),
+        "hash-command" => Some(run_hash_command(&args[1..])),
+        "trust-project" => Some(run_trust_project(&args[1..])),
+        "doctor" => Some(doctor::run_doctor(config)),
+        "export" => Some(match project_log_arg(&args[1..]) {
+            Ok((path, rest)) => export::run_export(&rest, &path),
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        }),
+        "--version" => {
+            println!("claude-permission-hook {}", VERSION);
+            Some(0)
+        }
+        "--validate" => Some(run_validate(&args[1..])),
+        "--check-update" => Some(update::run_check_update(config)),
+        "--test-webhook" => Some(webhook::run_test_webhook(config)),
+        _ => None,
+    }
+}
+
+/// `lint-config`: report duplicate, invalid, and overlapping patterns
+fn run_lint_config(config: &Config) -> i32 {
+    let issues = lint_config(config);
+    if issues.is_empty() {
+        match find_config_path() {
+            Some(path) => println!("No issues found in {}", path.display()),
+            None => println!("No config file found; built-in defaults have no issues"),
+        }
+        return 0;
+    }
+
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    println!("\n{} issue(s) found", issues.len());
+    1
+}
+
+/// `--validate [path]`: check that the config file (by default the one the
+/// hook loads) parses and that every pattern in it compiles
+fn run_validate(args: &[String]) -> i32 {
+    let path = match args.first() {
+        Some(path) => Some(PathBuf::from(path)),
+        None => find_config_path(),
+    };
+    let (config, source) = match &path {
+        Some(path) => match load_config_file(path) {
+            Ok(config) => (config, path.display().to_string()),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        },
+        None => (default_config(), "built-in defaults".to_string()),
+    };
+
+    let issues = invalid_patterns(&config);
+    if issues.is_empty() {
+        println!("All {} patterns in {} compile", pattern_count(&config), source);
+        return 0;
+    }
+    for issue in &issues {
+        println!("{}: {}: {}", source, issue.field, issue.message);
+    }
+    println!("\n{} invalid pattern(s); they are skipped when matching, so fix them", issues.len());
+    1
+}
+
+/// `trust-project [dir]`: apply the project config in `dir` (default: the
+/// current directory) or its nearest parent, as it is now
+fn run_trust_project(args: &[String]) -> i32 {
+    let dir = match args.first() {
+        Some(dir) => dir.clone(),
+        None => std::env::current_dir().unwrap_or_default().to_string_lossy().to_string(),
+    };
+    let Some(path) = find_project_config(&dir) else {
+        eprintln!("No {} found in {} or its parents", PROJECT_CONFIG_FILE, dir);
+        return 1;
+    };
+    match trust_project_config(&get_trusted_projects_path(), &path) {
+        Ok(hash) => {
+            println!("Trusted {} ({})", path.display(), &hash[..12]);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Take `--project <dir>` out of a log subcommand's arguments: the log to read
+/// (that project's `logging.per_project` log, or decisions.log) and the rest
+fn project_log_arg(args: &[String]) -> Result<(PathBuf, Vec<String>), String> {
+    let Some(i) = args.iter().position(|a| a == "--project") else {
+        return Ok((get_log_path(), args.to_vec()));
+    };
+    let dir = args.get(i + 1).ok_or("--project needs a directory")?;
+    let path = get_project_log_path(dir).ok_or_else(|| format!("No project log for '{}'", dir))?;
+    let rest = args[..i].iter().chain(&args[i + 2..]).cloned().collect();
+    Ok((path, rest))
+}
+
+/// `hash-command "<command>"`: print the hash for `auto_approve.approved_command_hashes`
+fn run_hash_command(args: &[String]) -> i32 {
+    match args {
+        [command] => {
+            println!("{}", command_hash(command));
+            0
+        }
+        _ => {
+            eprintln!("Usage: claude-permission-hook hash-command \"<command>\" (quote the whole command)");
+            1
+        }
+    }
+}
+
+/// Shown instead of waiting for input when the binary is run from a terminal
+const TERMINAL_USAGE: &str = "\
+claude-permission-hook is a Claude Code hook: it reads a JSON payload on stdin.
+Pipe one in, e.g.  echo '{\"tool_name\":\"Read\",\"tool_input\":{\"file_path\":\"x\"}}' | claude-permission-hook
+
+Subcommands: lint-config, watch, grant, remember, hash-command, trust-project, doctor, export, preview-webhook, --version, --check-update, --test-webhook, --validate";
+
+/// Usage message to print when stdin is a terminal (nobody is piping a payload)
+fn terminal_usage(stdin_is_terminal: bool) -> Option<&'static str> {
+    stdin_is_terminal.then_some(TERMINAL_USAGE)
+}
+
+/// The `--json-input <path>` argument, if given
+fn json_input_arg(args: &[String]) -> Option<Result<&str, String>> {
+    let i = args.iter().position(|a| a == "--json-input")?;
+    Some(args.get(i + 1).map(String::as_str).ok_or_else(|| "Usage: claude-permission-hook --json-input <path>".to_string()))
+}
+
+/// Parse a hook payload, or None if it isn't valid
+fn parse_payload(raw: &str) -> Option<HookInput> {
+    // Strip UTF-8 BOM if present (Windows PowerShell may add this)
+    let raw = raw.trim_start_matches('\u{feff}').trim();
+    serde_json::from_str(raw).ok()
+}
+
+/// Read a hook payload from a file (`--json-input`)
+fn read_payload_file(path: &Path) -> Result<HookInput, String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_payload(&raw).ok_or_else(|| format!("{} is not a valid hook payload", path.display()))
 }
 
 fn main() {
     let config = load_config();
+
+    // Subcommands run instead of the hook
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = run_subcommand(&config, &args) {
+        std::process::exit(code);
+    }
     let state_mgr = StateManager::new();
     let dedup_mgr = DedupManager::new();
 
@@ -378,21 +704,49 @@ fn main() {
     let mut circuit_breaker = CircuitBreaker::default();
     let mut rate_limiter = RateLimiter::default();
 
-    // Read JSON from stdin
-    let stdin = io::stdin();
-    let input_str: String = stdin.lock().lines()
-        .filter_map(|line| line.ok())
-        .collect();
-
-    // Strip UTF-8 BOM if present (Windows PowerShell may add this)
-    let input_str = input_str.trim_start_matches('\u{feff}').trim();
+    let input = match json_input_arg(&args) {
+        // `--json-input <path>`: read the payload from a file (debugging, scripted tests)
+        Some(Ok(path)) => match read_payload_file(Path::new(path)) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        None => {
+            // Read JSON from stdin, unless someone ran us by hand (it would block forever)
+            let stdin = io::stdin();
+            if let Some(usage) = terminal_usage(stdin.is_terminal()) {
+                eprintln!("{}", usage);
+                std::process::exit(1);
+            }
+            let input_str: String = stdin.lock().lines()
+                .map_while(Result::ok)
+                .collect();
 
-    // Parse payload
-    let input: HookInput = match serde_json::from_str(input_str) {
-        Ok(p) => p,
-        Err(_) => return, // Invalid input, let Claude handle it
+            match parse_payload(&input_str) {
+                Some(input) => input,
+                None => return, // Invalid input, let Claude handle it
+            }
+        }
     };
 
+    // Project rules from `.claude-permission-hook.json` in or above the cwd
+    let config = load_project_config(&config, &input.get_cwd()).unwrap_or_else(|e| {
+        logging::warn(&config, &e);
+        config.clone()
+    });
+
+    // `--explain`: describe the decision instead of acting on it
+    if args.iter().any(|a| a == "--explain") {
+        println!("{}", explain::explain(&config, &input));
+        std::process::exit(0);
+    }
+
     // Route based on hook event type
     let hook_event = if input.hook_event_name.is_empty() {
         "PreToolUse".to_string() // Default for backward compatibility
@@ -402,13 +756,16 @@ fn main() {
 
     debug(&config, &format!("Hook event: {}", hook_event));
 
-    match hook_event.as_str() {
-        "PreToolUse" => handle_pre_tool_use(&config, &input, &state_mgr),
-        "Stop" => handle_stop(&config, &input, &state_mgr, &dedup_mgr, &mut circuit_breaker, &mut rate_limiter),
-        "SubagentStop" => handle_subagent_stop(&config, &input, &state_mgr, &dedup_mgr, &mut circuit_breaker, &mut rate_limiter),
-        "Notification" => handle_notification(&config, &input, &state_mgr, &dedup_mgr, &mut circuit_breaker, &mut rate_limiter),
+    // The `events` config maps event names to handlers
+    let handler = config.event_handler(&hook_event);
+    match handler.as_str() {
+        "permission" => handle_pre_tool_use(&config, &input, &state_mgr),
+        "notify" => handle_stop(&config, &input, &state_mgr, &dedup_mgr, &mut circuit_breaker, &mut rate_limiter),
+        "subagent_stop" => handle_subagent_stop(&config, &input, &state_mgr, &dedup_mgr, &mut circuit_breaker, &mut rate_limiter),
+        "notification" => handle_notification(&config, &input, &state_mgr, &dedup_mgr, &mut circuit_breaker, &mut rate_limiter),
+        "ignore" => debug(&config, &format!("Ignoring hook event: {}", hook_event)),
         _ => {
-            debug(&config, &format!("Unknown hook event: {}", hook_event));
+            logging::warn(&config, &format!("Unknown handler '{}' for hook event {}", handler, hook_event));
             // Default to PreToolUse behavior
             handle_pre_tool_use(&config, &input, &state_mgr);
         }
@@ -422,7 +779,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use config::default_config;
+    use claude_permission_hook::config::default_config;
 
     #[test]
     fn test_config_loads() {
@@ -445,6 +802,46 @@ mod tests {
         assert_eq!(input.get_session_id(), "abc-123");
     }
 
+    #[test]
+    fn test_json_input_file() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(json_input_arg(&args(&["--json-input", "p.json"])), Some(Ok("p.json")));
+        assert!(matches!(json_input_arg(&args(&["--json-input"])), Some(Err(_))));
+        assert_eq!(json_input_arg(&args(&[])), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("payload.json");
+        std::fs::write(&path, "\u{feff}{\"tool_name\": \"Bash\", \"tool_input\": {\"command\": \"git status\"}}\n").unwrap();
+        let input = read_payload_file(&path).unwrap();
+        assert_eq!(input.get_tool_name(), "Bash");
+        assert!(matches!(evaluate(&default_config(), &input), Decision::Allow { .. }));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(read_payload_file(&path).unwrap_err().contains("not a valid hook payload"));
+        assert!(read_payload_file(&dir.path().join("missing.json")).is_err());
+    }
+
+    #[test]
+    fn test_terminal_usage() {
+        assert!(terminal_usage(false).is_none());
+        let usage = terminal_usage(true).unwrap();
+        assert!(usage.contains("JSON payload on stdin"));
+        assert!(usage.contains("doctor"));
+    }
+
+    #[test]
+    fn test_project_log_arg() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(project_log_arg(&args(&["--format", "json"])).unwrap(), (get_log_path(), args(&["--format", "json"])));
+
+        let (path, rest) = project_log_arg(&args(&["--from", "2024-01-01", "--project", "/home/dev/app"])).unwrap();
+        assert_eq!(path, get_project_log_path("/home/dev/app").unwrap());
+        assert_eq!(rest, args(&["--from", "2024-01-01"]));
+
+        assert!(project_log_arg(&args(&["--project"])).is_err());
+        assert!(project_log_arg(&args(&["--project", "/"])).is_err());
+    }
+
     #[test]
     fn test_status_detection() {
         assert_eq!(get_status_for_pre_tool_use("ExitPlanMode"), Status::PlanReady);
@@ -2,8 +2,19 @@
 
 use crate::analyzer::Status;
 use crate::config::Config;
-use crate::summary::{generate_session_name, get_status_title};
+use crate::platform;
+use crate::i18n::{localize_reason, translate};
+use crate::permission::{shell_words, watched_segment, Decision};
+use crate::summary::{generate_session_name, localized_status_title};
 use notify_rust::Notification;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long `auto_deny.on_deny_command` may run before it is killed
+const ON_DENY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Longest command passed to `on_deny_command` in PERMISSION_HOOK_DETAILS
+const ON_DENY_DETAILS_LEN: usize = 500;
 
 /// Send a desktop notification
 pub fn send_notification(
@@ -18,19 +29,14 @@ pub fn send_notification(
         return Ok(());
     }
 
-    let title = get_status_title(status);
+    let title = localized_status_title(status, &config.locale);
     let session_name = generate_session_name(session_id, cwd, git_branch);
 
-    // Build notification body
-    let body = if summary.is_empty() {
-        session_name
-    } else {
-        format!("{}\n{}", session_name, summary)
-    };
+    let body = notification_body(&session_name, summary, config.notifications.desktop.single_line);
 
     // Send notification
     let result = Notification::new()
-        .summary(title)
+        .summary(&title)
         .body(&body)
         .appname("Claude Code")
         .timeout(notify_rust::Timeout::Milliseconds(5000))
@@ -42,27 +48,37 @@ pub fn send_notification(
     }
 }
 
-/// Send an alert notification for blocked/denied commands
+/// Notification body: session name, then the summary on its own line or, in
+/// single-line mode, after a dash with its newlines collapsed
+fn notification_body(session_name: &str, summary: &str, single_line: bool) -> String {
+    if summary.is_empty() {
+        session_name.to_string()
+    } else if single_line {
+        format!("{} \u{2014} {}", session_name, summary.split_whitespace().collect::<Vec<_>>().join(" "))
+    } else {
+        format!("{}\n{}", session_name, summary)
+    }
+}
+
+/// Send an alert notification for blocked/denied commands.
+/// `segment` (the part of the command that matched) is shown instead of the
+/// command prefix when available, so it isn't lost to truncation.
 pub fn send_alert_notification(
     config: &Config,
     tool: &str,
     reason: &str,
     details: Option<&str>,
+    segment: Option<&str>,
+    description: Option<&str>,
 ) -> Result<(), String> {
     if !config.notifications.desktop.enabled {
         return Ok(());
     }
 
-    let title = "BLOCKED";
-
-    // Build prominent alert body
-    let detail_str = details.unwrap_or("-");
-    let body = format!(
-        "Command denied by security policy\n\n{}: {}\nReason: {}",
-        tool,
-        truncate_detail(detail_str, 60),
-        reason
-    );
+    let locale = &config.locale;
+    let title = translate(locale, "alert.title").unwrap_or("BLOCKED");
+    let shown = if config.notifications.alert_show_segment { segment.or(details) } else { details };
+    let body = alert_body(locale, tool, reason, shown, description);
 
     // Send notification with longer timeout for alerts
     let result = Notification::new()
@@ -78,6 +94,113 @@ pub fn send_alert_notification(
     }
 }
 
+/// Run `auto_deny.on_deny_command` for a denied request.
+///
+/// The decision goes in environment variables: PERMISSION_HOOK_TOOL,
+/// PERMISSION_HOOK_REASON and PERMISSION_HOOK_DETAILS (the command or path,
+/// truncated). Its stdin, stdout and stderr are detached so it can't touch
+/// the hook's protocol output; it is killed after a few seconds.
+pub fn run_on_deny_command(config: &Config, tool: &str, reason: &str, details: Option<&str>) -> Result<(), String> {
+    let words = shell_words(&config.auto_deny.on_deny_command);
+    let Some((program, args)) = words.split_first() else {
+        return Ok(());
+    };
+
+    let mut child = Command::new(program)
+        .args(args)
+        .env("PERMISSION_HOOK_TOOL", tool)
+        .env("PERMISSION_HOOK_REASON", reason)
+        .env("PERMISSION_HOOK_DETAILS", truncate_detail(details.unwrap_or(""), ON_DENY_DETAILS_LEN))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run on_deny_command: {}", e))?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return Ok(()),
+            Ok(None) if started.elapsed() < ON_DENY_TIMEOUT => std::thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err("on_deny_command timed out".into());
+            }
+        }
+    }
+}
+
+/// Message for a watched command (`notifications.watch_patterns`), or None if
+/// the request doesn't match a watch pattern
+pub fn watch_message(config: &Config, tool_name: &str, input: &serde_json::Value, decision: &Decision) -> Option<String> {
+    let segment = watched_segment(config, tool_name, input)?;
+    let outcome = match decision {
+        Decision::Allow { .. } => "allowed",
+        Decision::Deny { .. } => "denied",
+        Decision::Prompt { .. } => "prompted",
+    };
+    Some(format!("Watched command ({}): {}", outcome, segment))
+}
+
+/// Send a desktop notification for a watched command
+pub fn send_watch_notification(config: &Config, message: &str) -> Result<(), String> {
+    if !config.notifications.desktop.enabled {
+        return Ok(());
+    }
+
+    let result = Notification::new()
+        .summary("👀 Watched Command")
+        .body(&truncate_detail(message, 200))
+        .appname("Claude Code")
+        .timeout(notify_rust::Timeout::Milliseconds(5000))
+        .show();
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Failed to send watch notification: {}", e)),
+    }
+}
+
+/// Send the low-priority "still working" heartbeat for a long session
+pub fn send_heartbeat_notification(config: &Config, session_name: &str) -> Result<(), String> {
+    if !config.notifications.desktop.enabled {
+        return Ok(());
+    }
+
+    let mut notification = Notification::new();
+    notification
+        .summary("⏳ Still Working")
+        .body(session_name)
+        .appname("Claude Code")
+        .timeout(notify_rust::Timeout::Milliseconds(3000));
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.urgency(notify_rust::Urgency::Low);
+
+    match notification.show() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Failed to send heartbeat notification: {}", e)),
+    }
+}
+
+/// Build the alert body for a denied command in the given locale.
+/// Bash's description, when given, comes before the command: `Bash: Clean up — rm -rf /`
+fn alert_body(locale: &str, tool: &str, reason: &str, details: Option<&str>, description: Option<&str>) -> String {
+    let detail_str = truncate_detail(details.unwrap_or("-"), 60);
+    let shown = match description {
+        Some(description) => format!("{} — {}", truncate_detail(description, 60), detail_str),
+        None => detail_str,
+    };
+    format!(
+        "{}\n\n{}: {}\n{}: {}",
+        translate(locale, "alert.body").unwrap_or("Command denied by security policy"),
+        tool,
+        shown,
+        translate(locale, "alert.reason").unwrap_or("Reason"),
+        localize_reason(locale, reason)
+    )
+}
+
 /// Truncate detail string for display (UTF-8 safe)
 fn truncate_detail(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
@@ -127,6 +250,12 @@ pub fn should_notify(config: &Config, status: Status) -> bool {
         return false;
     }
 
+    if config.notifications.only_when_unfocused
+        && !allowed_by_focus(platform::terminal_is_focused())
+    {
+        return false;
+    }
+
     match status {
         Status::TaskComplete | Status::ReviewComplete => true,
         Status::Question => true,
@@ -137,11 +266,23 @@ pub fn should_notify(config: &Config, status: Status) -> bool {
     }
 }
 
+/// Focus gate for `only_when_unfocused`: unknown focus counts as unfocused
+fn allowed_by_focus(focused: Option<bool>) -> bool {
+    focused != Some(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::default_config;
 
+    #[test]
+    fn test_notification_body() {
+        assert_eq!(notification_body("proj", "Done", false), "proj\nDone");
+        assert_eq!(notification_body("proj", "Done\n  all tests\npass", true), "proj \u{2014} Done all tests pass");
+        assert_eq!(notification_body("proj", "", true), "proj");
+    }
+
     #[test]
     fn test_should_notify_enabled() {
         let mut config = default_config();
@@ -161,4 +302,102 @@ mod tests {
         assert!(!should_notify(&config, Status::TaskComplete));
         assert!(!should_notify(&config, Status::Question));
     }
+
+    #[test]
+    fn test_alert_body_localized() {
+        let body = alert_body("en", "Bash", "dangerous pattern", Some("rm -rf /"), None);
+        assert_eq!(body, "Command denied by security policy\n\nBash: rm -rf /\nReason: dangerous pattern");
+
+        let body = alert_body("es", "Bash", "dangerous pattern", Some("rm -rf /"), None);
+        assert!(body.starts_with("Comando denegado"));
+        assert!(body.ends_with("Motivo: patrón peligroso"));
+    }
+
+    #[test]
+    fn test_alert_body_shows_matched_segment() {
+        let mut config = default_config();
+        config.features.trust_mode = false;
+        let command = format!("echo {} && rm -rf /", "x".repeat(80));
+        let input = serde_json::json!({"command": command});
+        let m = crate::permission::check_auto_deny(&config, "Bash", &input, "").unwrap();
+
+        // The raw command prefix would hide the dangerous part
+        assert!(!alert_body("en", "Bash", &m.reason, Some(&command), None).contains("rm -rf /"));
+
+        let segment = m.segment.as_deref();
+        let body = alert_body("en", "Bash", &m.reason, segment.or(Some(&command)), None);
+        assert!(body.contains("Bash: rm -rf /"));
+    }
+
+    #[test]
+    fn test_alert_body_with_description() {
+        let body = alert_body("en", "Bash", "dangerous pattern", Some("rm -rf /"), Some("Clean up old files"));
+        assert_eq!(
+            body,
+            "Command denied by security policy\n\nBash: Clean up old files — rm -rf /\nReason: dangerous pattern"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_on_deny_command() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let record = dir.path().join("record.txt");
+        let script = dir.path().join("on-deny.sh");
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$1|$PERMISSION_HOOK_TOOL|$PERMISSION_HOOK_REASON|$PERMISSION_HOOK_DETAILS\" > {}\necho noise; echo noise >&2\n",
+                record.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut config = default_config();
+        assert!(run_on_deny_command(&config, "Bash", "dangerous pattern", Some("rm -rf /")).is_ok());
+        assert!(!record.exists());
+
+        config.auto_deny.on_deny_command = format!("{} siem", script.display());
+        run_on_deny_command(&config, "Bash", "dangerous pattern", Some("rm -rf /")).unwrap();
+        assert_eq!(std::fs::read_to_string(&record).unwrap(), "siem|Bash|dangerous pattern|rm -rf /\n");
+
+        config.auto_deny.on_deny_command = dir.path().join("missing.sh").display().to_string();
+        assert!(run_on_deny_command(&config, "Bash", "dangerous pattern", None).is_err());
+    }
+
+    #[test]
+    fn test_watch_message() {
+        let mut config = default_config();
+        config.notifications.watch_patterns = vec![r"^git\s+push".into(), r"^terraform\s+apply".into()];
+        let allow = Decision::Allow { reason: "trust mode enabled".into() };
+
+        let input = serde_json::json!({"command": "cargo test && git push origin main"});
+        assert_eq!(
+            watch_message(&config, "Bash", &input, &allow).as_deref(),
+            Some("Watched command (allowed): git push origin main")
+        );
+
+        let deny = Decision::Deny { reason: "dangerous pattern".into(), segment: None };
+        let input = serde_json::json!({"command": "git push --force"});
+        assert_eq!(
+            watch_message(&config, "Bash", &input, &deny).as_deref(),
+            Some("Watched command (denied): git push --force")
+        );
+
+        // Unwatched commands and other tools produce nothing
+        let input = serde_json::json!({"command": "git status"});
+        assert!(watch_message(&config, "Bash", &input, &allow).is_none());
+        let input = serde_json::json!({"file_path": "git push"});
+        assert!(watch_message(&config, "Write", &input, &allow).is_none());
+    }
+
+    #[test]
+    fn test_allowed_by_focus() {
+        assert!(!allowed_by_focus(Some(true)));
+        assert!(allowed_by_focus(Some(false)));
+        // Unsupported platforms are treated as always unfocused
+        assert!(allowed_by_focus(None));
+    }
 }
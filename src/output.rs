@@ -0,0 +1,214 @@
+//! Rendering of PreToolUse decisions to stdout/stderr
+//!
+//! Writers are passed in so the exact hook output can be tested; `main` uses
+//! `io::stdout()` and `io::stderr()`.
+
+use crate::config::Config;
+use crate::i18n::{localize_reason, reason_code};
+use crate::logging::{emit, StderrKind};
+use crate::permission::{Decision, HookResponse};
+use std::io::Write;
+
+/// Write the hook output for a decision and return the process exit code.
+///
+/// - Allow: JSON response on stdout, exit 0
+/// - Deny: `[permission-hook] DENY: ...` on stderr, exit 2
+/// - Prompt: no stdout, exit 0 (passthrough to native permissions), or with
+///   `output.prompt_mode = "ask_json"` a JSON response with decision "ask"
+pub fn render_decision(
+    config: &Config,
+    tool_name: &str,
+    details: Option<&str>,
+    decision: &Decision,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    let code = render_human(config, tool_name, details, decision, stdout, stderr);
+    // Machine-readable line for scripts scraping stderr; never on stdout,
+    // which carries the hook protocol
+    if config.logging.emit_result_trailer {
+        let _ = writeln!(stderr, "{}", result_trailer(tool_name, decision));
+    }
+    code
+}
+
+/// Stable code for a decision: `DENY_DANGEROUS_PATTERN`, `ALLOW_SAFE_PATTERN`, ...
+/// Reasons that aren't short codes (LLM answers, prompt details) get `_OTHER`;
+/// quoted details after a code (the matched pattern) are left out.
+pub fn result_code(decision: &Decision) -> String {
+    let (prefix, reason) = match decision {
+        Decision::Allow { reason } => ("ALLOW", reason),
+        Decision::Deny { reason, .. } => ("DENY", reason),
+        Decision::Prompt { reason } => ("PROMPT", reason),
+    };
+    let reason = reason_code(reason);
+    let is_code = reason.len() <= 40
+        && !reason.is_empty()
+        && reason.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-');
+    let suffix = if is_code {
+        reason.to_uppercase().replace([' ', '-'], "_")
+    } else {
+        "OTHER".to_string()
+    };
+    format!("{}_{}", prefix, suffix)
+}
+
+/// `PH-RESULT decision=deny code=DENY_DANGEROUS_PATTERN tool=Bash`
+pub fn result_trailer(tool_name: &str, decision: &Decision) -> String {
+    let name = match decision {
+        Decision::Allow { .. } => "allow",
+        Decision::Deny { .. } => "deny",
+        Decision::Prompt { .. } => "prompt",
+    };
+    format!("PH-RESULT decision={} code={} tool={}", name, result_code(decision), tool_name)
+}
+
+fn render_human(
+    config: &Config,
+    tool_name: &str,
+    details: Option<&str>,
+    decision: &Decision,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> i32 {
+    match decision {
+        Decision::Allow { reason } => {
+            let reason = localize_reason(&config.locale, reason);
+            emit(
+                stderr,
+                config,
+                StderrKind::Decision,
+                &format!("[permission-hook] ALLOW: {} - {} ({})", tool_name, reason, details.unwrap_or("no details")),
+            );
+            let response = HookResponse::allow(&reason).with_suppress_output(config.output.suppress_output.allow);
+            let _ = writeln!(stdout, "{}", serde_json::to_string(&response).unwrap_or_default());
+            0
+        }
+        Decision::Deny { reason, .. } => {
+            emit(
+                stderr,
+                config,
+                StderrKind::Deny,
+                &format!("[permission-hook] DENY: {} - {}", tool_name, localize_reason(&config.locale, reason)),
+            );
+            2
+        }
+        Decision::Prompt { reason } => {
+            emit(stderr, config, StderrKind::Decision, &format!("[permission-hook] {}", reason));
+            if config.output.prompt_mode == "ask_json" {
+                let response = HookResponse::ask(reason).with_suppress_output(config.output.suppress_output.ask);
+                let _ = writeln!(stdout, "{}", serde_json::to_string(&response).unwrap_or_default());
+            }
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    fn render(config: &Config, decision: &Decision) -> (i32, String, String) {
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let code = render_decision(config, "Bash", Some("rm -rf /"), decision, &mut stdout, &mut stderr);
+        (code, String::from_utf8(stdout).unwrap(), String::from_utf8(stderr).unwrap())
+    }
+
+    #[test]
+    fn test_render_deny() {
+        let config = default_config();
+        let decision = Decision::Deny { reason: "dangerous pattern".into(), segment: None };
+
+        let (code, stdout, stderr) = render(&config, &decision);
+        assert_eq!(code, 2);
+        assert!(stdout.is_empty());
+        assert_eq!(stderr, "[permission-hook] DENY: Bash - dangerous pattern\n");
+    }
+
+    #[test]
+    fn test_render_result_trailer() {
+        let mut config = default_config();
+        config.logging.emit_result_trailer = true;
+        let decision = Decision::Deny { reason: "dangerous pattern".into(), segment: None };
+
+        let (code, stdout, stderr) = render(&config, &decision);
+        assert_eq!(code, 2);
+        assert!(stdout.is_empty());
+        assert_eq!(
+            stderr,
+            "[permission-hook] DENY: Bash - dangerous pattern\nPH-RESULT decision=deny code=DENY_DANGEROUS_PATTERN tool=Bash\n"
+        );
+
+        // Allow keeps stdout pure JSON
+        let (_, stdout, stderr) = render(&config, &Decision::Allow { reason: "read-only MCP".into() });
+        assert!(serde_json::from_str::<serde_json::Value>(&stdout).is_ok());
+        assert_eq!(stderr, "PH-RESULT decision=allow code=ALLOW_READ_ONLY_MCP tool=Bash\n");
+
+        let prompt = Decision::Prompt { reason: "Prompting user for: Bash (rm -rf /)".into() };
+        assert_eq!(result_code(&prompt), "PROMPT_OTHER");
+    }
+
+    #[test]
+    fn test_render_deny_localized() {
+        let mut config = default_config();
+        config.locale = "es".into();
+        let decision = Decision::Deny { reason: "dangerous pattern".into(), segment: None };
+
+        let (code, _, stderr) = render(&config, &decision);
+        assert_eq!(code, 2);
+        assert_eq!(stderr, "[permission-hook] DENY: Bash - patrón peligroso\n");
+    }
+
+    #[test]
+    fn test_render_allow_and_prompt() {
+        let mut config = default_config();
+        config.logging.stderr_level = "decisions".into();
+
+        let (code, stdout, stderr) = render(&config, &Decision::Allow { reason: "auto-approve tool".into() });
+        assert_eq!(code, 0);
+        assert!(stdout.contains("\"permissionDecision\":\"allow\""));
+        assert_eq!(stderr, "[permission-hook] ALLOW: Bash - auto-approve tool (rm -rf /)\n");
+
+        let (code, stdout, stderr) = render(&config, &Decision::Prompt { reason: "prompt path".into() });
+        assert_eq!(code, 0);
+        assert!(stdout.is_empty());
+        assert_eq!(stderr, "[permission-hook] prompt path\n");
+    }
+
+    #[test]
+    fn test_render_prompt_modes() {
+        let mut config = default_config();
+        let decision = Decision::Prompt { reason: "prompt path".into() };
+
+        // Passthrough: nothing on stdout
+        assert_eq!(config.output.prompt_mode, "passthrough");
+        let (code, stdout, _) = render(&config, &decision);
+        assert_eq!(code, 0);
+        assert!(stdout.is_empty());
+
+        config.output.prompt_mode = "ask_json".into();
+        let (code, stdout, _) = render(&config, &decision);
+        assert_eq!(code, 0);
+        let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(json["hookSpecificOutput"]["permissionDecision"], "ask");
+        assert_eq!(json["hookSpecificOutput"]["permissionDecisionReason"], "prompt path");
+        assert_eq!(json["suppressOutput"], true);
+    }
+
+    #[test]
+    fn test_render_respects_suppress_output() {
+        let mut config = default_config();
+        let allow = Decision::Allow { reason: "auto-approve tool".into() };
+
+        let (_, stdout, _) = render(&config, &allow);
+        let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(json["suppressOutput"], true);
+
+        config.output.suppress_output.allow = false;
+        let (_, stdout, _) = render(&config, &allow);
+        let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(json["suppressOutput"], false);
+    }
+}
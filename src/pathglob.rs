@@ -0,0 +1,204 @@
+//! Glob matching for protected paths (`auto_deny.protected_paths_glob`)
+//!
+//! Globs are matched against normalized paths: backslashes become `/`, `.`
+//! and `..` are resolved, relative paths are made absolute against the
+//! request's working directory, and symlinks in
+//! the part of the path that exists are followed. So `/etc/**` also catches
+//! `/etc/../etc/passwd` and a symlink pointing into `/etc`, which a regex
+//! over the raw `file_path` misses.
+//!
+//! `**` matches across directories, `*` and `?` within one path component.
+//! Globs with a drive letter (`C:\Windows\**`), and all globs on Windows,
+//! match case-insensitively.
+
+use crate::patterns;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Resolve `.`/`..`, unify separators to `/` and make the path absolute
+/// (relative paths against `cwd`, or the current directory if it's empty).
+/// No filesystem access.
+pub fn normalize(path: &str, cwd: &str) -> String {
+    let mut path = path.replace('\\', "/");
+    if path == "~" || path.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            path = format!("{}{}", home.to_string_lossy().replace('\\', "/"), &path[1..]);
+        }
+    }
+
+    let (root, rest) = split_root(&path);
+    let (root, rest) = match root {
+        Some(root) => (root, rest.to_string()),
+        None => {
+            let cwd = match cwd {
+                "" => std::env::current_dir().unwrap_or_default().to_string_lossy().replace('\\', "/"),
+                cwd => cwd.replace('\\', "/"),
+            };
+            let (cwd_root, cwd_rest) = split_root(&cwd);
+            (cwd_root.unwrap_or_else(|| "/".into()), format!("{}/{}", cwd_rest, rest))
+        }
+    };
+
+    let mut parts: Vec<&str> = Vec::new();
+    for part in rest.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    format!("{}{}", root, parts.join("/"))
+}
+
+/// Split off the root: `/`, a drive (`C:/`, uppercased) or a UNC prefix (`//`)
+fn split_root(path: &str) -> (Option<String>, &str) {
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let rest = path[2..].trim_start_matches('/');
+        return (Some(format!("{}:/", (bytes[0] as char).to_ascii_uppercase())), rest);
+    }
+    if let Some(rest) = path.strip_prefix("//") {
+        return (Some("//".into()), rest);
+    }
+    match path.strip_prefix('/') {
+        Some(rest) => (Some("/".into()), rest),
+        None => (None, path),
+    }
+}
+
+/// `normalize`, then follow symlinks in the longest prefix that exists. The
+/// rest (e.g. a file about to be written) is appended unchanged.
+pub fn canonicalize(path: &str, cwd: &str) -> String {
+    let normalized = normalize(path, cwd);
+    let full = PathBuf::from(&normalized);
+    for existing in full.ancestors() {
+        let Ok(resolved) = fs::canonicalize(existing) else {
+            continue;
+        };
+        let rest = full.strip_prefix(existing).unwrap_or(Path::new(""));
+        let resolved = resolved.join(rest).to_string_lossy().to_string();
+        // Windows canonical paths carry the verbatim prefix
+        return normalize(resolved.strip_prefix(r"\\?\").unwrap_or(&resolved), cwd);
+    }
+    normalized
+}
+
+/// Regex for a normalized glob
+fn glob_to_regex(glob: &str) -> String {
+    let case_insensitive = cfg!(windows) || split_root(glob).0.is_some_and(|root| root.ends_with(":/"));
+    let mut out = String::from(if case_insensitive { "(?i)^" } else { "^" });
+    let mut rest = glob;
+    while !rest.is_empty() {
+        // `/etc/**` matches /etc itself and everything under it; `/a/**/b`
+        // matches /a/b and /a/x/y/b
+        if let Some(after) = rest.strip_prefix("/**") {
+            if after.is_empty() || after.starts_with('/') {
+                out.push_str("(?:/.*)?");
+                rest = after;
+                continue;
+            }
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            out.push_str(".*");
+            rest = after;
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        match c {
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            _ => out.push_str(&regex::escape(&c.to_string())),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out.push('$');
+    out
+}
+
+/// The glob with symlinks resolved in its literal (wildcard-free) leading directories
+fn canonical_glob(glob: &str, cwd: &str) -> String {
+    let normalized = normalize(glob, cwd);
+    let (root, rest) = split_root(&normalized);
+    let root = root.unwrap_or_default();
+    let literal: Vec<&str> = rest.split('/').take_while(|part| !part.contains(['*', '?'])).collect();
+    if literal.len() == rest.split('/').count() {
+        return canonicalize(&normalized, cwd);
+    }
+    let prefix = format!("{}{}", root, literal.join("/"));
+    let canonical_prefix = canonicalize(&prefix, cwd);
+    format!("{}{}", canonical_prefix.trim_end_matches('/'), &normalized[prefix.len()..])
+}
+
+/// Whether a path matches any of the globs, comparing both the lexically
+/// normalized and the symlink-resolved forms. Relative paths and globs are
+/// taken relative to `cwd`.
+pub fn matches_any(globs: &[String], path: &str, cwd: &str) -> bool {
+    if globs.is_empty() || path.is_empty() {
+        return false;
+    }
+    let regexes: Vec<String> = globs
+        .iter()
+        .flat_map(|glob| [glob_to_regex(&normalize(glob, cwd)), glob_to_regex(&canonical_glob(glob, cwd))])
+        .collect();
+    let set = patterns::cached(&regexes);
+    set.is_match(&normalize(path, cwd)) || set.is_match(&canonicalize(path, cwd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("/etc/../etc/./passwd", ""), "/etc/passwd");
+        assert_eq!(normalize("/../../etc//hosts", ""), "/etc/hosts");
+        assert_eq!(normalize(r"c:\Windows\System32\..\win.ini", ""), "C:/Windows/win.ini");
+        assert!(normalize("src/../Cargo.toml", "").ends_with("/Cargo.toml"));
+        assert!(!normalize("src/../Cargo.toml", "").contains(".."));
+
+        // Relative paths are resolved against the request's cwd
+        assert_eq!(normalize("../../../etc/passwd", "/home/dev/app"), "/etc/passwd");
+        assert_eq!(normalize("./src/main.rs", "/home/dev/app"), "/home/dev/app/src/main.rs");
+        assert_eq!(normalize("/etc/hosts", "/home/dev/app"), "/etc/hosts");
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let matches = |glob: &str, path: &str| regex::Regex::new(&glob_to_regex(glob)).unwrap().is_match(path);
+        assert!(matches("/etc/**", "/etc"));
+        assert!(matches("/etc/**", "/etc/ssl/certs/ca.pem"));
+        assert!(!matches("/etc/**", "/etcetera/x"));
+        assert!(matches("/home/*/.ssh/**", "/home/alice/.ssh/id_rsa"));
+        assert!(!matches("/home/*/.ssh/**", "/home/alice/work/.ssh/id_rsa"));
+        assert!(matches("/srv/**/secrets.?ml", "/srv/app/config/secrets.yml"));
+        assert!(matches("C:/Windows/**", "c:/windows/system32/drivers/etc/hosts"));
+    }
+
+    #[test]
+    fn test_matches_any_normalizes_paths() {
+        let globs = vec!["/etc/**".to_string(), r"C:\Windows\**".to_string()];
+        assert!(matches_any(&globs, "/etc/../etc/passwd", ""));
+        assert!(matches_any(&globs, "/tmp/../etc/shadow", ""));
+        assert!(matches_any(&globs, r"C:\Windows\System32\config\SAM", ""));
+        assert!(!matches_any(&globs, "/tmp/etc/passwd", ""));
+        assert!(!matches_any(&[], "/etc/passwd", ""));
+        assert!(matches_any(&globs, "../../etc/passwd", "/home/dev"));
+        assert!(!matches_any(&globs, "etc/passwd", "/home/dev"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_matches_any_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret = dir.path().join("secret");
+        fs::create_dir(&secret).unwrap();
+        std::os::unix::fs::symlink(&secret, dir.path().join("link")).unwrap();
+
+        let globs = vec![format!("{}/**", secret.display())];
+        assert!(matches_any(&globs, &format!("{}/link/key.pem", dir.path().display()), ""));
+        assert!(matches_any(&globs, &format!("{}/link/new/file.txt", dir.path().display()), ""));
+        assert!(!matches_any(&globs, &format!("{}/other.txt", dir.path().display()), ""));
+    }
+}
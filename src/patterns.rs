@@ -0,0 +1,240 @@
+//! Compiled regex pattern sets
+//!
+//! A category's patterns are combined into one `RegexSet`, so a single pass
+//! over a command checks all of them. Sets are built once per process, the
+//! first time a list is matched against, and cached by their pattern list.
+//! If a set can't be built, its patterns are matched one at a time instead.
+//!
+//! Matching is linear-time, but compiled sizes aren't: user patterns are
+//! compiled with a size limit, and matched text is capped at `MAX_INPUT_LEN`.
+
+use crate::config::Config;
+use crate::logging;
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+/// Compiled size limit per user pattern; larger ones are rejected
+pub const SIZE_LIMIT: usize = 1 << 20;
+
+/// Only this many bytes of a text are matched against patterns
+pub const MAX_INPUT_LEN: usize = 64 * 1024;
+
+/// Compile a user-supplied pattern within `SIZE_LIMIT`
+pub fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).size_limit(SIZE_LIMIT).dfa_size_limit(SIZE_LIMIT).build()
+}
+
+/// The part of `text` that is matched: at most `MAX_INPUT_LEN` bytes (UTF-8 safe)
+pub fn bounded(text: &str) -> &str {
+    if text.len() <= MAX_INPUT_LEN {
+        return text;
+    }
+    let mut end = MAX_INPUT_LEN;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// How a `PatternSet` matches
+#[derive(Debug)]
+enum Matcher {
+    Set(RegexSet),
+    /// The patterns one by one, when the combined set couldn't be built
+    Each(Vec<Regex>),
+}
+
+/// Patterns compiled into a single `RegexSet`
+#[derive(Debug)]
+pub struct PatternSet {
+    /// The list the set was built from, invalid patterns included
+    patterns: Vec<String>,
+    matcher: Matcher,
+    /// Source of each pattern in the set, by set index
+    sources: Vec<String>,
+    /// Patterns left out because they don't compile, with the error
+    pub invalid: Vec<String>,
+}
+
+impl PatternSet {
+    /// Compile patterns, leaving out (and recording) any that don't compile
+    /// or exceed `SIZE_LIMIT`
+    pub fn new(patterns: &[String]) -> Self {
+        Self::with_set_limit(patterns, SIZE_LIMIT.saturating_mul(patterns.len().max(1)))
+    }
+
+    fn with_set_limit(patterns: &[String], limit: usize) -> Self {
+        let mut sources = Vec::new();
+        let mut regexes = Vec::new();
+        let mut invalid = Vec::new();
+        for pattern in patterns {
+            match compile(pattern) {
+                Ok(regex) => {
+                    sources.push(pattern.clone());
+                    regexes.push(regex);
+                }
+                Err(e) => invalid.push(format!("Skipping invalid pattern '{}': {}", pattern, e)),
+            }
+        }
+        let matcher = match RegexSetBuilder::new(&sources).size_limit(limit).dfa_size_limit(limit).build() {
+            Ok(set) => Matcher::Set(set),
+            Err(_) => Matcher::Each(regexes),
+        };
+        Self { patterns: patterns.to_vec(), matcher, sources, invalid }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.first_index(bounded(text)).is_some()
+    }
+
+    /// The first pattern (in config order) that matches
+    pub fn first_match(&self, text: &str) -> Option<&str> {
+        self.first_index(bounded(text)).map(|i| self.sources[i].as_str())
+    }
+
+    fn first_index(&self, text: &str) -> Option<usize> {
+        match &self.matcher {
+            Matcher::Set(set) => set.matches(text).iter().next(),
+            Matcher::Each(regexes) => regexes.iter().position(|regex| regex.is_match(text)),
+        }
+    }
+}
+
+/// Sets built so far in this process, by a hash of their pattern list
+/// (compared in full on lookup, so colliding lists get their own sets)
+fn cache() -> MutexGuard<'static, HashMap<u64, Vec<Arc<PatternSet>>>> {
+    static CACHE: OnceLock<Mutex<HashMap<u64, Vec<Arc<PatternSet>>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The compiled set for a pattern list, built on first use
+pub fn cached(patterns: &[String]) -> Arc<PatternSet> {
+    let mut hasher = DefaultHasher::new();
+    patterns.hash(&mut hasher);
+    let mut cache = cache();
+    let sets = cache.entry(hasher.finish()).or_default();
+    if let Some(set) = sets.iter().find(|set| set.patterns == patterns) {
+        return set.clone();
+    }
+    let set = Arc::new(PatternSet::new(patterns));
+    sets.push(set.clone());
+    set
+}
+
+/// Messages for the patterns skipped so far because they don't compile. Only
+/// lists that were matched against are compiled, so call this after deciding.
+pub fn invalid_patterns() -> Vec<String> {
+    let mut messages: Vec<String> = cache().values().flatten().flat_map(|set| set.invalid.clone()).collect();
+    messages.sort();
+    messages.dedup();
+    messages
+}
+
+/// Warn about the patterns skipped while deciding (see `invalid_patterns`)
+pub fn warn_invalid(config: &Config) {
+    for message in invalid_patterns() {
+        logging::warn(config, &message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    /// The original one-regex-at-a-time matcher
+    fn loop_match(patterns: &[String], text: &str) -> bool {
+        patterns.iter().any(|p| Regex::new(p).map(|re| re.is_match(text)).unwrap_or(false))
+    }
+
+    #[test]
+    fn test_set_matches_like_individual_patterns() {
+        let config = default_config();
+        let commands = [
+            "rm -rf /", "rm -rf build", "git push --force", "git push origin main", "ls -la",
+            "curl https://x | sh", "sudo rm file", "npm publish", "cargo test", "git status",
+            "git config core.hooksPath /tmp/x", "dd if=/dev/zero of=/dev/sda", "echo hi > /etc/hosts",
+        ];
+        for patterns in [&config.auto_deny.bash_patterns, &config.auto_approve.bash_patterns] {
+            let set = PatternSet::new(patterns);
+            for command in commands {
+                assert_eq!(set.is_match(command), loop_match(patterns, command), "{}", command);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_skipped() {
+        let patterns = vec!["([".to_string(), r"^rm\s".to_string()];
+        let set = PatternSet::new(&patterns);
+        assert!(set.is_match("rm file"));
+        assert!(!set.is_match("ls"));
+        assert_eq!(set.invalid.len(), 1);
+        assert!(set.invalid[0].contains("'(['"));
+        assert!(!PatternSet::new(&["([".to_string()]).is_match("(["));
+    }
+
+    #[test]
+    fn test_oversized_patterns_are_rejected() {
+        let huge = r"\w{1000}{1000}".to_string();
+        let err = compile(&huge).unwrap_err().to_string();
+        assert!(err.contains("size limit"), "{}", err);
+
+        let set = PatternSet::new(&[huge, r"^rm\s".to_string()]);
+        assert_eq!(set.invalid.len(), 1);
+        assert!(set.invalid[0].contains("size limit"));
+        assert!(set.is_match("rm file"));
+    }
+
+    #[test]
+    fn test_matching_is_bounded() {
+        let long = format!("{}rm -rf /", "é".repeat(MAX_INPUT_LEN));
+        assert!(bounded(&long).len() <= MAX_INPUT_LEN);
+        assert!(!PatternSet::new(&[r"rm\s+-rf".to_string()]).is_match(&long));
+        assert_eq!(bounded("ls -la"), "ls -la");
+    }
+
+    #[test]
+    fn test_first_match_maps_to_source_pattern() {
+        let patterns: Vec<String> = vec![r"^ls\b".into(), "([".into(), r"git\s+push.*--force".into(), r"git\s+push".into()];
+        let set = PatternSet::new(&patterns);
+
+        assert_eq!(set.first_match("git push --force origin main"), Some(r"git\s+push.*--force"));
+        assert_eq!(set.first_match("git push origin main"), Some(r"git\s+push"));
+        assert_eq!(set.first_match("ls -la"), Some(r"^ls\b"));
+        assert_eq!(set.first_match("cargo build"), None);
+    }
+
+    #[test]
+    fn test_cached_reuses_sets() {
+        let patterns = vec![r"^cached-test\s".to_string()];
+        assert!(Arc::ptr_eq(&cached(&patterns), &cached(&patterns)));
+        assert!(Arc::ptr_eq(&cached(&patterns), &cached(&patterns.clone())));
+        assert!(!Arc::ptr_eq(&cached(&patterns), &cached(&[r"^cached-test2\s".to_string()])));
+    }
+
+    #[test]
+    fn test_set_build_failure_falls_back_to_each_pattern() {
+        let patterns: Vec<String> = vec![r"^ls\b".into(), "([".into(), r"git\s+push.*--force".into(), r"git\s+push".into()];
+        let set = PatternSet::with_set_limit(&patterns, 1);
+        assert!(matches!(set.matcher, Matcher::Each(_)));
+        assert_eq!(set.first_match("git push --force origin main"), Some(r"git\s+push.*--force"));
+        assert_eq!(set.first_match("git push origin main"), Some(r"git\s+push"));
+        assert!(set.is_match("ls -la"));
+        assert!(!set.is_match("cargo build"));
+        assert_eq!(set.invalid.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_patterns_reported_once_used() {
+        let patterns = vec![r"^reported-test\s".to_string(), r"reported-test(".to_string()];
+        assert!(!invalid_patterns().iter().any(|m| m.contains("reported-test(")));
+
+        assert!(cached(&patterns).is_match("reported-test x"));
+        let reported: Vec<_> = invalid_patterns().into_iter().filter(|m| m.contains("reported-test(")).collect();
+        assert_eq!(reported.len(), 1);
+    }
+}
@@ -1,7 +1,9 @@
 //! Cross-platform utilities
 
+use std::io::Read;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Get current Unix timestamp in seconds
 pub fn current_timestamp() -> i64 {
@@ -54,6 +56,136 @@ pub fn get_git_branch(cwd: &str) -> Option<String> {
         })
 }
 
+/// Write a file only the current user can read or write (0600 on Unix),
+/// through a uniquely named temporary file and a rename, so concurrent writers
+/// never see or clobber each other's half-written file
+pub fn write_private_file(path: &Path, content: &str) -> std::io::Result<()> {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(
+        ".{}.{}-{}-{}.tmp",
+        name,
+        std::process::id(),
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let result = options
+        .open(&tmp)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, content.as_bytes()))
+        .and_then(|_| std::fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// How long `git_is_clean` waits for `git status`
+const GIT_STATUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Whether the working tree at `cwd` has no uncommitted changes (untracked
+/// files count as changes). None outside a git repository, if git can't be
+/// run, or if `git status` doesn't finish within `GIT_STATUS_TIMEOUT`.
+pub fn git_is_clean(cwd: &str) -> Option<bool> {
+    let mut child = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(if cwd.is_empty() { "." } else { cwd })
+        // Don't take the index lock a concurrent git command may need
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // Drain stdout on a thread so a large status can't fill the pipe and stall git
+    let mut stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < GIT_STATUS_TIMEOUT => std::thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    let output = reader.join().ok()?.ok()?;
+    status.success().then_some(output.is_empty())
+}
+
+/// Best-effort check whether the terminal running the hook has focus.
+/// Returns None when focus cannot be determined on this platform.
+pub fn terminal_is_focused() -> Option<bool> {
+    terminal_is_focused_impl()
+}
+
+/// macOS: compare the frontmost application with the terminal from TERM_PROGRAM
+#[cfg(target_os = "macos")]
+fn terminal_is_focused_impl() -> Option<bool> {
+    let app = match std::env::var("TERM_PROGRAM").ok()?.as_str() {
+        "Apple_Terminal" => "Terminal",
+        "iTerm.app" => "iTerm2",
+        "WezTerm" => "wezterm-gui",
+        "vscode" => "Code",
+        other => return Some(frontmost_app()? == other),
+    };
+    Some(frontmost_app()? == app)
+}
+
+#[cfg(target_os = "macos")]
+fn frontmost_app() -> Option<String> {
+    let output = std::process::Command::new("osascript")
+        .args([
+            "-e",
+            "tell application \"System Events\" to get name of first application process whose frontmost is true",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Linux (X11): compare the active window with the terminal's WINDOWID
+#[cfg(target_os = "linux")]
+fn terminal_is_focused_impl() -> Option<bool> {
+    let window_id: u64 = std::env::var("WINDOWID").ok()?.trim().parse().ok()?;
+    let output = std::process::Command::new("xdotool")
+        .arg("getactivewindow")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let active: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(active == window_id)
+}
+
+/// Other platforms: focus detection not supported
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn terminal_is_focused_impl() -> Option<bool> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,4 +207,57 @@ mod tests {
         let dir = temp_dir();
         assert!(dir.exists());
     }
+
+    #[test]
+    fn test_write_private_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sub").join("state.json");
+        write_private_file(&path, "one").unwrap();
+        write_private_file(&path, "two").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "two");
+        // No temporary files are left behind
+        assert_eq!(std::fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_git_is_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(cwd)
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            return; // git not installed
+        }
+
+        assert_eq!(git_is_clean(cwd), Some(true));
+
+        std::fs::write(dir.path().join("file.txt"), "one").unwrap();
+        assert_eq!(git_is_clean(cwd), Some(false));
+
+        assert!(git(&["add", "file.txt"]));
+        assert!(git(&["commit", "-q", "-m", "initial"]));
+        assert_eq!(git_is_clean(cwd), Some(true));
+
+        std::fs::write(dir.path().join("file.txt"), "two").unwrap();
+        assert_eq!(git_is_clean(cwd), Some(false));
+    }
+
+    #[test]
+    fn test_git_is_clean_outside_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(git_is_clean(dir.path().to_str().unwrap()), None);
+        assert_eq!(git_is_clean(dir.path().join("missing").to_str().unwrap()), None);
+    }
 }
@@ -0,0 +1,180 @@
+//! Commands remembered for the rest of a session (`remember` subcommand)
+//!
+//! When a Bash command prompts, the hook records it for its session; running
+//! `claude-permission-hook remember` afterwards approves that exact command
+//! for the rest of the session. Everything lives in the config dir, which the
+//! agent can't write, so only the user can remember a command:
+//!
+//! `{"<session_id>": {"last_prompted": "make deploy", "last_prompt_time": 1700000000, "commands": ["make deploy"], "updated": 1700000000}}`
+
+use crate::config::get_config_dir;
+use crate::platform;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Sessions untouched for this long are dropped whenever the file is written
+const SESSION_TTL_SECONDS: i64 = 7 * 24 * 3600;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionMemory {
+    /// Last Bash command that prompted, for `remember` without arguments
+    #[serde(default)]
+    pub last_prompted: String,
+    #[serde(default)]
+    pub last_prompt_time: i64,
+    /// Commands the user chose to auto-approve for the rest of this session
+    #[serde(default)]
+    pub commands: Vec<String>,
+    #[serde(default)]
+    pub updated: i64,
+}
+
+type Memory = BTreeMap<String, SessionMemory>;
+
+/// Remembered commands of all sessions
+pub fn remembered_path() -> PathBuf {
+    get_config_dir().join("remembered.json")
+}
+
+fn load(path: &Path) -> Memory {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Change one session's entry, dropping sessions idle longer than `SESSION_TTL_SECONDS`
+fn update(path: &Path, session_id: &str, now: i64, change: impl FnOnce(&mut SessionMemory)) -> Result<(), String> {
+    // Read-modify-write, so hold the lock throughout
+    let _lock = crate::logging::FileLock::acquire(path);
+    let mut memory = load(path);
+    memory.retain(|_, session| now - session.updated < SESSION_TTL_SECONDS);
+    let session = memory.entry(session_id.to_string()).or_default();
+    change(session);
+    session.updated = now;
+
+    let content = serde_json::to_string_pretty(&memory).map_err(|e| e.to_string())?;
+    platform::write_private_file(path, &content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Record the Bash command a session just prompted for
+pub fn record_prompted(path: &Path, session_id: &str, command: &str, now: i64) -> Result<(), String> {
+    update(path, session_id, now, |session| {
+        session.last_prompted = command.to_string();
+        session.last_prompt_time = now;
+    })
+}
+
+/// Auto-approve an exact command for the rest of a session
+pub fn remember(path: &Path, session_id: &str, command: &str, now: i64) -> Result<(), String> {
+    update(path, session_id, now, |session| {
+        if !session.commands.iter().any(|c| c == command) {
+            session.commands.push(command.to_string());
+        }
+    })
+}
+
+/// Check if a command was remembered in this session
+pub fn is_remembered(path: &Path, session_id: &str, command: &str) -> bool {
+    load(path)
+        .get(session_id)
+        .is_some_and(|session| session.commands.iter().any(|c| c == command.trim()))
+}
+
+/// The session that prompted most recently (the one the user is looking at)
+/// and the command it prompted for, as (session_id, command)
+pub fn last_prompted(path: &Path) -> Option<(String, String)> {
+    load(path)
+        .into_iter()
+        .filter(|(_, session)| !session.last_prompted.is_empty())
+        .max_by_key(|(_, session)| session.last_prompt_time)
+        .map(|(session_id, session)| (session_id, session.last_prompted))
+}
+
+/// The command `session_id` last prompted for
+fn last_prompted_in(path: &Path, session_id: &str) -> Option<String> {
+    load(path)
+        .remove(session_id)
+        .map(|session| session.last_prompted)
+        .filter(|command| !command.is_empty())
+}
+
+/// Run the `remember` subcommand: `remember [<session_id> [<command>]]`.
+/// Without a command, remembers the session's last prompted command; without
+/// a session, the last prompt's session.
+pub fn run_remember(args: &[String]) -> i32 {
+    let path = remembered_path();
+    let (session_id, command) = match args {
+        [] => match last_prompted(&path) {
+            Some(last) => last,
+            None => {
+                eprintln!("No prompted command to remember");
+                return 1;
+            }
+        },
+        [session_id] => match last_prompted_in(&path, session_id) {
+            Some(command) => (session_id.clone(), command),
+            None => {
+                eprintln!("No prompted command to remember in session {}", session_id);
+                return 1;
+            }
+        },
+        [session_id, command @ ..] => (session_id.clone(), command.join(" ")),
+    };
+
+    match remember(&path, &session_id, command.trim(), platform::current_timestamp()) {
+        Ok(()) => {
+            println!("Remembered '{}' for session {}", command.trim(), session_id);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remember_is_session_scoped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("remembered.json");
+
+        record_prompted(&path, "s1", "make deploy", 1_000).unwrap();
+        assert!(!is_remembered(&path, "s1", "make deploy"));
+
+        remember(&path, "s1", "make deploy", 1_010).unwrap();
+        remember(&path, "s1", "make deploy", 1_020).unwrap();
+        assert!(is_remembered(&path, "s1", "make deploy"));
+        assert!(!is_remembered(&path, "s1", "make deploy --force"));
+        assert!(!is_remembered(&path, "s2", "make deploy"));
+        assert_eq!(load(&path)["s1"].commands, vec!["make deploy".to_string()]);
+    }
+
+    #[test]
+    fn test_last_prompted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("remembered.json");
+        assert_eq!(last_prompted(&path), None);
+
+        record_prompted(&path, "s1", "make deploy", 1_000).unwrap();
+        record_prompted(&path, "s2", "npm publish", 1_100).unwrap();
+        assert_eq!(last_prompted(&path), Some(("s2".into(), "npm publish".into())));
+        assert_eq!(last_prompted_in(&path, "s1").as_deref(), Some("make deploy"));
+        assert_eq!(last_prompted_in(&path, "s3"), None);
+    }
+
+    #[test]
+    fn test_idle_sessions_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("remembered.json");
+        remember(&path, "old", "make deploy", 1_000).unwrap();
+        remember(&path, "new", "make test", 1_000 + SESSION_TTL_SECONDS).unwrap();
+        assert_eq!(load(&path).keys().collect::<Vec<_>>(), vec!["new"]);
+    }
+}
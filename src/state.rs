@@ -24,6 +24,37 @@ pub struct SessionState {
     pub last_notification_message: String,
     #[serde(default)]
     pub cwd: String,
+    #[serde(default)]
+    pub question_window_start: i64,
+    #[serde(default)]
+    pub pending_questions: u32,
+    /// PreToolUse decisions made so far, for `ambiguous.warmup_prompts`
+    #[serde(default)]
+    pub decision_count: u32,
+    /// Latest decisions, oldest first, as context for the LLM (`llm.include_context`)
+    #[serde(default)]
+    pub recent_decisions: Vec<String>,
+    /// Start of the current stretch of activity and its latest tool call, for heartbeats
+    #[serde(default)]
+    pub activity_start_time: i64,
+    #[serde(default)]
+    pub last_activity_time: i64,
+    #[serde(default)]
+    pub last_heartbeat_time: i64,
+}
+
+impl SessionState {
+    /// Whether a "still working" heartbeat is due at `now`: the session has been
+    /// busy (no gap longer than `interval`) and nothing was notified for `interval` seconds
+    pub fn heartbeat_due(&self, now: i64, interval: i64) -> bool {
+        if interval <= 0 || self.last_activity_time == 0 || now - self.last_activity_time >= interval {
+            return false;
+        }
+        let quiet_since = self.activity_start_time
+            .max(self.last_notification_time)
+            .max(self.last_heartbeat_time);
+        now - quiet_since >= interval
+    }
 }
 
 /// State manager for session state
@@ -163,6 +194,33 @@ impl Manager {
         Ok(elapsed < cooldown_seconds)
     }
 
+    /// Record a question and decide whether to notify for it.
+    /// Returns Some(count) to notify for `count` questions (this one plus any coalesced
+    /// since the last notification), or None if it falls inside the grouping window.
+    pub fn record_question(&self, session_id: &str, window_seconds: i64) -> Result<Option<u32>, String> {
+        let mut state = self.load(session_id)?.unwrap_or_else(|| SessionState {
+            session_id: session_id.to_string(),
+            ..Default::default()
+        });
+
+        let now = platform::current_timestamp();
+        let in_window = window_seconds > 0
+            && state.question_window_start > 0
+            && now - state.question_window_start < window_seconds;
+
+        if in_window {
+            state.pending_questions += 1;
+            self.save(&state)?;
+            return Ok(None);
+        }
+
+        let count = state.pending_questions + 1;
+        state.pending_questions = 0;
+        state.question_window_start = now;
+        self.save(&state)?;
+        Ok(Some(count))
+    }
+
     /// Check if message is a duplicate
     pub fn is_duplicate_message(&self, session_id: &str, message: &str, window_seconds: i64) -> Result<bool, String> {
         if window_seconds <= 0 {
@@ -195,16 +253,72 @@ impl Manager {
         Ok(normalize(message) == normalize(&state.last_notification_message))
     }
 
+    /// Count a PreToolUse decision for the session
+    pub fn record_decision(&self, session_id: &str) -> Result<(), String> {
+        let mut state = self.load(session_id)?.unwrap_or_else(|| SessionState {
+            session_id: session_id.to_string(),
+            ..Default::default()
+        });
+
+        state.decision_count = state.decision_count.saturating_add(1);
+
+        self.save(&state)
+    }
+
+    /// Add a decision summary to the session's recent decisions, keeping the last `keep`
+    pub fn record_recent_decision(&self, session_id: &str, summary: &str, keep: usize) -> Result<(), String> {
+        let mut state = self.load(session_id)?.unwrap_or_else(|| SessionState {
+            session_id: session_id.to_string(),
+            ..Default::default()
+        });
+
+        state.recent_decisions.push(summary.to_string());
+        let excess = state.recent_decisions.len().saturating_sub(keep);
+        state.recent_decisions.drain(..excess);
+
+        self.save(&state)
+    }
+
+    /// The session's recent decision summaries, oldest first
+    pub fn recent_decisions(&self, session_id: &str) -> Vec<String> {
+        self.load(session_id).ok().flatten().map(|s| s.recent_decisions).unwrap_or_default()
+    }
+
+    /// PreToolUse decisions made so far in the session
+    pub fn decision_count(&self, session_id: &str) -> u32 {
+        self.load(session_id).ok().flatten().map_or(0, |s| s.decision_count)
+    }
+
+    /// Record tool activity and report whether a heartbeat is due (recording it if so)
+    pub fn record_activity(&self, session_id: &str, heartbeat_seconds: i64) -> Result<bool, String> {
+        let mut state = self.load(session_id)?.unwrap_or_else(|| SessionState {
+            session_id: session_id.to_string(),
+            ..Default::default()
+        });
+
+        let now = platform::current_timestamp();
+        let due = state.heartbeat_due(now, heartbeat_seconds);
+        // A long pause ends the stretch; the next one starts now
+        if state.last_activity_time == 0 || now - state.last_activity_time >= heartbeat_seconds {
+            state.activity_start_time = now;
+        }
+        state.last_activity_time = now;
+        if due {
+            state.last_heartbeat_time = now;
+        }
+
+        self.save(&state)?;
+        Ok(due)
+    }
+
     /// Update state based on status
     pub fn update_state(&self, session_id: &str, status: Status, tool: &str, cwd: &str) -> Result<(), String> {
         match status {
             Status::TaskComplete | Status::ReviewComplete => {
                 self.update_task_complete(session_id)?;
             }
-            Status::PlanReady | Status::Question => {
-                if !tool.is_empty() {
-                    self.update_interactive_tool(session_id, tool, cwd)?;
-                }
+            Status::PlanReady | Status::Question if !tool.is_empty() => {
+                self.update_interactive_tool(session_id, tool, cwd)?;
             }
             _ => {}
         }
@@ -235,6 +349,34 @@ impl Manager {
 
         Ok(())
     }
+
+    /// Keep only the `max_files` most recently written session-state files
+    /// (0 keeps all), so a burst of sessions can't pile up files before
+    /// `cleanup` ages them out
+    pub fn cleanup_keep_newest(&self, max_files: usize) -> Result<(), String> {
+        if max_files == 0 {
+            return Ok(());
+        }
+
+        let entries = fs::read_dir(&self.temp_dir)
+            .map_err(|e| format!("Failed to read temp dir: {}", e))?;
+
+        let mut files: Vec<(std::time::SystemTime, PathBuf)> = entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.starts_with("claude-session-state-") && name.ends_with(".json")
+            })
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        for (_, path) in files.into_iter().skip(max_files) {
+            let _ = fs::remove_file(&path);
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for Manager {
@@ -255,6 +397,49 @@ mod tests {
         format!("test-state-{}-{:?}", platform::current_timestamp(), std::thread::current().id())
     }
 
+    #[test]
+    fn test_cleanup_keep_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let mgr = Manager { temp_dir: dir.path().to_path_buf() };
+
+        for i in 0..5 {
+            mgr.save(&SessionState { session_id: format!("s{}", i), ..Default::default() }).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        fs::write(dir.path().join("unrelated.json"), "{}").unwrap();
+
+        mgr.cleanup_keep_newest(0).unwrap();
+        assert!(mgr.load("s0").unwrap().is_some());
+
+        mgr.cleanup_keep_newest(3).unwrap();
+        let survivors: Vec<bool> = (0..5).map(|i| mgr.load(&format!("s{}", i)).unwrap().is_some()).collect();
+        assert_eq!(survivors, [false, false, true, true, true]);
+        assert!(dir.path().join("unrelated.json").exists());
+    }
+
+    #[test]
+    fn test_heartbeat_due() {
+        let busy = SessionState {
+            activity_start_time: 1000,
+            last_activity_time: 1550,
+            ..Default::default()
+        };
+        assert!(busy.heartbeat_due(1600, 600));
+        assert!(!busy.heartbeat_due(1599, 600));
+        assert!(!busy.heartbeat_due(1600, 0));
+
+        // A recent notification or heartbeat resets the clock
+        let notified = SessionState { last_notification_time: 1300, ..busy.clone() };
+        assert!(!notified.heartbeat_due(1600, 600));
+        assert!(notified.heartbeat_due(1900, 600));
+        let beat = SessionState { last_heartbeat_time: 1300, ..busy.clone() };
+        assert!(!beat.heartbeat_due(1600, 600));
+
+        // Idle for longer than the interval: not an active session
+        assert!(!busy.heartbeat_due(2200, 600));
+        assert!(!SessionState::default().heartbeat_due(1600, 600));
+    }
+
     #[test]
     fn test_load_nonexistent() {
         let mgr = test_manager();
@@ -287,6 +472,23 @@ mod tests {
         mgr.delete(&session_id).unwrap();
     }
 
+    #[test]
+    fn test_recent_decisions_keep_latest() {
+        let mgr = test_manager();
+        let session_id = unique_session_id();
+
+        assert!(mgr.recent_decisions(&session_id).is_empty());
+        for i in 0..4 {
+            mgr.record_recent_decision(&session_id, &format!("allow Bash: step {}", i), 3).unwrap();
+        }
+        assert_eq!(
+            mgr.recent_decisions(&session_id),
+            vec!["allow Bash: step 1", "allow Bash: step 2", "allow Bash: step 3"]
+        );
+
+        mgr.delete(&session_id).unwrap();
+    }
+
     #[test]
     fn test_delete() {
         let mgr = test_manager();
@@ -351,6 +553,44 @@ mod tests {
         mgr.delete(&session_id).unwrap();
     }
 
+    #[test]
+    fn test_record_question_coalesces_within_window() {
+        let mgr = test_manager();
+        let session_id = unique_session_id();
+
+        // First question notifies, second within the window is coalesced
+        assert_eq!(mgr.record_question(&session_id, 30).unwrap(), Some(1));
+        assert_eq!(mgr.record_question(&session_id, 30).unwrap(), None);
+
+        // Once the window has passed, the next notification carries the coalesced count
+        let mut state = mgr.load(&session_id).unwrap().unwrap();
+        state.question_window_start -= 31;
+        mgr.save(&state).unwrap();
+        assert_eq!(mgr.record_question(&session_id, 30).unwrap(), Some(2));
+
+        // Cleanup
+        mgr.delete(&session_id).unwrap();
+    }
+
+    #[test]
+    fn test_record_question_beyond_window_notifies_each() {
+        let mgr = test_manager();
+        let session_id = unique_session_id();
+
+        assert_eq!(mgr.record_question(&session_id, 30).unwrap(), Some(1));
+
+        let mut state = mgr.load(&session_id).unwrap().unwrap();
+        state.question_window_start -= 31;
+        mgr.save(&state).unwrap();
+        assert_eq!(mgr.record_question(&session_id, 30).unwrap(), Some(1));
+
+        // Zero window disables grouping
+        assert_eq!(mgr.record_question(&session_id, 0).unwrap(), Some(1));
+
+        // Cleanup
+        mgr.delete(&session_id).unwrap();
+    }
+
     #[test]
     fn test_is_duplicate_normalized() {
         let mgr = test_manager();
@@ -1,6 +1,7 @@
 //! Summary generator for notification messages
 
 use crate::analyzer::Status;
+use crate::i18n;
 use crate::jsonl::{self, Message};
 use regex::Regex;
 
@@ -156,6 +157,17 @@ pub fn truncate_smart(text: &str, max_len: usize) -> String {
     format!("{}...", truncated)
 }
 
+/// Summary for a notification covering several coalesced questions
+pub fn grouped_question_summary(count: u32, latest: &str) -> String {
+    if count <= 1 {
+        latest.to_string()
+    } else if latest.is_empty() {
+        format!("{} questions waiting", count)
+    } else {
+        format!("{} questions waiting - latest: {}", count, latest)
+    }
+}
+
 /// Get status-specific title prefix with emoji
 pub fn get_status_title(status: Status) -> &'static str {
     match status {
@@ -169,6 +181,13 @@ pub fn get_status_title(status: Status) -> &'static str {
     }
 }
 
+/// Status title in the given locale (English if untranslated)
+pub fn localized_status_title(status: Status, locale: &str) -> String {
+    i18n::translate(locale, &format!("title.{}", status.as_str()))
+        .unwrap_or_else(|| get_status_title(status))
+        .to_string()
+}
+
 /// Generate session display name from session ID and optional context
 pub fn generate_session_name(session_id: &str, cwd: &str, git_branch: Option<&str>) -> String {
     let mut parts = Vec::new();
@@ -182,7 +201,7 @@ pub fn generate_session_name(session_id: &str, cwd: &str, git_branch: Option<&st
 
     // Add folder name from cwd
     if !cwd.is_empty() {
-        if let Some(folder) = cwd.split(['/', '\\']).last() {
+        if let Some(folder) = cwd.split(['/', '\\']).next_back() {
             if !folder.is_empty() {
                 parts.push(folder.to_string());
             }
@@ -252,6 +271,20 @@ mod tests {
         assert!(result.len() <= 33); // 30 + "..."
     }
 
+    #[test]
+    fn test_grouped_question_summary() {
+        assert_eq!(grouped_question_summary(1, "Which DB?"), "Which DB?");
+        assert_eq!(grouped_question_summary(3, "Which DB?"), "3 questions waiting - latest: Which DB?");
+        assert_eq!(grouped_question_summary(2, ""), "2 questions waiting");
+    }
+
+    #[test]
+    fn test_localized_status_title() {
+        assert_eq!(localized_status_title(Status::Question, "de"), "❓ Frage");
+        assert_eq!(localized_status_title(Status::PlanReady, "en"), get_status_title(Status::PlanReady));
+        assert_eq!(localized_status_title(Status::PlanReady, ""), get_status_title(Status::PlanReady));
+    }
+
     #[test]
     fn test_get_status_title() {
         assert!(get_status_title(Status::TaskComplete).contains("Task Complete"));
@@ -0,0 +1,46 @@
+//! Stand-in HTTP endpoint for tests of the webhook and LLM clients
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::thread::JoinHandle;
+
+/// Answers each connection in turn with `(status, body)`, or holds it open
+/// without answering for None. Returns the base URL and a handle yielding the
+/// body of every request served
+pub(crate) fn serve(responses: Vec<Option<(u16, &'static str)>>) -> (String, JoinHandle<Vec<String>>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let Ok((mut stream, _)) = listener.accept() else { break };
+            // Read the request so the client isn't cut off mid-send
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            let _ = reader.read_exact(&mut body);
+            requests.push(String::from_utf8_lossy(&body).into_owned());
+
+            match response {
+                Some((status, body)) => {
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                }
+                None => std::thread::sleep(std::time::Duration::from_secs(3)),
+            }
+        }
+        requests
+    });
+    (url, handle)
+}
@@ -129,44 +129,37 @@ pub fn check_for_update(config: &Config) -> Option<(String, String)> {
         return None;
     }
 
-    // Perform the check
-    logging::debug(config, &format!("Checking for updates from {}", config.updates.github_repo));
-
-    match fetch_latest_version(&config.updates.github_repo) {
-        Ok(latest) => {
-            // Update state
-            state.last_check = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-
-            let has_update = is_newer_version(VERSION, &latest);
-
-            if has_update {
-                state.latest_version = Some(latest.clone());
-                state.notified = false;
-                let _ = state.save();
-                Some((VERSION.to_string(), latest))
-            } else {
-                state.latest_version = None;
-                state.notified = false;
-                let _ = state.save();
-                None
-            }
-        }
+    match fetch_and_record(config, &mut state) {
+        Ok(latest) => latest.map(|latest| (VERSION.to_string(), latest)),
         Err(e) => {
             logging::debug(config, &format!("Update check failed: {}", e));
-            // Update last_check to avoid hammering the API on failure
-            state.last_check = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            let _ = state.save();
             None
         }
     }
 }
 
+/// Ask GitHub for the latest release and record the check; Some(tag) if it's
+/// newer than this build. Failed checks are recorded too, so the API isn't
+/// hammered.
+fn fetch_and_record(config: &Config, state: &mut UpdateState) -> Result<Option<String>, String> {
+    logging::debug(config, &format!("Checking for updates from {}", config.updates.github_repo));
+
+    let result = fetch_latest_version(&config.updates.github_repo);
+    state.last_check = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    if let Ok(ref latest) = result {
+        let has_update = is_newer_version(VERSION, latest);
+        state.latest_version = has_update.then(|| latest.clone());
+        state.notified = false;
+    }
+    let _ = state.save();
+
+    let latest = result?;
+    Ok(is_newer_version(VERSION, &latest).then_some(latest))
+}
+
 /// Mark that the user has been notified about the update
 pub fn mark_notified() {
     let mut state = UpdateState::load();
@@ -174,10 +167,58 @@ pub fn mark_notified() {
     let _ = state.save();
 }
 
+/// Release page for a version tag
+pub fn release_url(repo: &str, tag: &str) -> String {
+    format!("https://github.com/{}/releases/tag/{}", repo, tag)
+}
+
+/// `--check-update`: ask GitHub now, whatever the check interval, and print
+/// the current and latest versions
+pub fn run_check_update(config: &Config) -> i32 {
+    if !config.updates.check_enabled {
+        println!("Update checks are disabled; set \"updates\": {{ \"check_enabled\": true }} in your config to use --check-update");
+        return 1;
+    }
+
+    let latest = match fetch_and_record(config, &mut UpdateState::load()) {
+        Ok(latest) => latest,
+        Err(e) => {
+            eprintln!("Update check failed: {}", e);
+            return 1;
+        }
+    };
+
+    println!("Current version: v{}", VERSION);
+    match latest {
+        Some(latest) => {
+            println!("Latest version:  {}", latest);
+            println!("Release: {}", release_url(&config.updates.github_repo, &latest));
+            mark_notified();
+        }
+        None => println!("You're up to date"),
+    }
+    0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_release_url() {
+        assert_eq!(
+            release_url("tantk/permission-hook", "v1.2.0"),
+            "https://github.com/tantk/permission-hook/releases/tag/v1.2.0"
+        );
+    }
+
+    #[test]
+    fn test_check_update_disabled() {
+        let config = crate::config::default_config();
+        assert!(!config.updates.check_enabled);
+        assert_eq!(run_check_update(&config), 1);
+    }
+
     #[test]
     fn test_is_newer_version() {
         assert!(is_newer_version("1.0.0", "1.0.1"));
@@ -0,0 +1,189 @@
+//! Live monitoring of decisions.log (`watch` subcommand)
+
+use std::fs::{self, File};
+use std::io::{IsTerminal, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Follows a growing log file, reopening it after truncation or rotation
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+    file_id: Option<u64>,
+    partial: String,
+}
+
+impl LogTail {
+    /// Start following from the current end of the file
+    pub fn from_end(path: PathBuf) -> Self {
+        let meta = fs::metadata(&path).ok();
+        LogTail {
+            offset: meta.as_ref().map(|m| m.len()).unwrap_or(0),
+            file_id: meta.as_ref().and_then(file_id),
+            path,
+            partial: String::new(),
+        }
+    }
+
+    /// Start following from the beginning of the file
+    pub fn from_start(path: PathBuf) -> Self {
+        let file_id = fs::metadata(&path).ok().as_ref().and_then(file_id);
+        LogTail { path, offset: 0, file_id, partial: String::new() }
+    }
+
+    /// Read complete lines appended since the last call.
+    /// A trailing line without a newline is held back until it is finished.
+    pub fn read_new_lines(&mut self) -> Vec<String> {
+        let Ok(meta) = fs::metadata(&self.path) else {
+            return Vec::new();
+        };
+
+        // Rotated (replaced by a new file) or truncated: start over
+        let id = file_id(&meta);
+        if id != self.file_id || meta.len() < self.offset {
+            self.file_id = id;
+            self.offset = 0;
+            self.partial.clear();
+        }
+
+        if meta.len() == self.offset {
+            return Vec::new();
+        }
+
+        let Ok(mut file) = File::open(&self.path) else {
+            return Vec::new();
+        };
+        if file.seek(SeekFrom::Start(self.offset)).is_err() {
+            return Vec::new();
+        }
+
+        let mut buf = Vec::new();
+        if file.read_to_end(&mut buf).is_err() {
+            return Vec::new();
+        }
+        self.offset += buf.len() as u64;
+        self.partial.push_str(&String::from_utf8_lossy(&buf));
+
+        let mut lines = Vec::new();
+        while let Some(pos) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=pos).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        lines
+    }
+}
+
+/// Identity of the file behind a path, used to detect rotation
+#[cfg(unix)]
+fn file_id(meta: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.ino())
+}
+
+/// Without inodes, rotation is only detected when the new file is smaller
+#[cfg(not(unix))]
+fn file_id(_meta: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Color a decisions.log line by its decision column (Y/N/ASK)
+pub fn colorize(line: &str) -> String {
+    let color = match line.split(',').nth(2) {
+        Some("Y") => "32",
+        Some("N") => "31",
+        Some("ASK") => "33",
+        _ => return line.to_string(),
+    };
+    format!("\x1b[{}m{}\x1b[0m", color, line)
+}
+
+/// Run the `watch` subcommand: print new decisions until interrupted
+pub fn run_watch(path: PathBuf) -> i32 {
+    let use_color = std::io::stdout().is_terminal();
+    println!("Watching {} (Ctrl+C to stop)", path.display());
+
+    let mut tail = LogTail::from_end(path);
+    loop {
+        for line in tail.read_new_lines() {
+            if line.starts_with("timestamp,") {
+                continue;
+            }
+            if use_color {
+                println!("{}", colorize(&line));
+            } else {
+                println!("{}", line);
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn append(path: &std::path::Path, text: &str) {
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_read_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("decisions.log");
+        append(&path, "old line\n");
+
+        let mut tail = LogTail::from_end(path.clone());
+        assert!(tail.read_new_lines().is_empty());
+
+        append(&path, "first\nsecond\n");
+        assert_eq!(tail.read_new_lines(), vec!["first", "second"]);
+
+        // Partial lines wait for their newline
+        append(&path, "thi");
+        assert!(tail.read_new_lines().is_empty());
+        append(&path, "rd\n");
+        assert_eq!(tail.read_new_lines(), vec!["third"]);
+    }
+
+    #[test]
+    fn test_reopen_after_truncate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("decisions.log");
+        append(&path, "one\ntwo\n");
+
+        let mut tail = LogTail::from_start(path.clone());
+        assert_eq!(tail.read_new_lines(), vec!["one", "two"]);
+
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(tail.read_new_lines(), vec!["new"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reopen_after_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("decisions.log");
+        append(&path, "before rotation that is long\n");
+
+        let mut tail = LogTail::from_end(path.clone());
+        fs::rename(&path, dir.path().join("decisions.log.1")).unwrap();
+        append(&path, "after rotation that is even longer\n");
+
+        assert_eq!(tail.read_new_lines(), vec!["after rotation that is even longer"]);
+    }
+
+    #[test]
+    fn test_colorize() {
+        assert_eq!(colorize("2024-01-01T00:00:00,Bash,Y,ok,-"), "\x1b[32m2024-01-01T00:00:00,Bash,Y,ok,-\x1b[0m");
+        assert!(colorize("t,Bash,N,bad,-").starts_with("\x1b[31m"));
+        assert!(colorize("t,Bash,ASK,ambiguous,-").starts_with("\x1b[33m"));
+        assert_eq!(colorize("not a log line"), "not a log line");
+    }
+}
@@ -1,8 +1,9 @@
 //! Webhook notifications with retry and circuit breaker
 
 use crate::analyzer::Status;
-use crate::config::Config;
-use crate::summary::get_status_title;
+use crate::config::{expand_env_vars, Config};
+use crate::logging;
+use crate::summary::{localized_status_title, truncate_smart};
 use serde::Serialize;
 use std::time::{Duration, Instant};
 
@@ -26,6 +27,67 @@ impl From<&str> for WebhookPreset {
     }
 }
 
+impl WebhookPreset {
+    /// Longest message text the platform reliably accepts (Slack attachment
+    /// text, Discord embed description, Telegram message); None for custom
+    pub fn max_text_len(&self) -> Option<usize> {
+        match self {
+            WebhookPreset::Slack => Some(3000),
+            WebhookPreset::Discord => Some(2000),
+            WebhookPreset::Telegram => Some(4096),
+            WebhookPreset::Custom => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            WebhookPreset::Slack => "slack",
+            WebhookPreset::Discord => "discord",
+            WebhookPreset::Telegram => "telegram",
+            WebhookPreset::Custom => "custom",
+        }
+    }
+}
+
+/// Host part of a URL (lowercase, without userinfo or port)
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    host.split(':').next().unwrap_or(host).to_lowercase()
+}
+
+/// Preset implied by a well-known webhook host, if any
+fn preset_for_host(host: &str) -> Option<WebhookPreset> {
+    let is = |domain: &str| host == domain || host.ends_with(&format!(".{}", domain));
+    if is("slack.com") {
+        Some(WebhookPreset::Slack)
+    } else if is("discord.com") || is("discordapp.com") {
+        Some(WebhookPreset::Discord)
+    } else if is("telegram.org") {
+        Some(WebhookPreset::Telegram)
+    } else {
+        None
+    }
+}
+
+/// Best-effort check that the preset fits the URL. Returns a warning when the
+/// URL points at a known service other than the preset's; unknown hosts
+/// (proxies, self-hosted endpoints) are never flagged.
+pub fn preset_url_mismatch(preset: &str, url: &str) -> Option<String> {
+    let preset = WebhookPreset::from(preset);
+    let expected = preset_for_host(&url_host(url))?;
+    if expected == preset {
+        return None;
+    }
+    Some(format!(
+        "webhook preset is '{}' but the URL looks like a {} webhook; did you mean preset '{}'?",
+        preset.name(),
+        expected.name(),
+        expected.name()
+    ))
+}
+
 /// Circuit breaker state
 #[derive(Debug)]
 pub struct CircuitBreaker {
@@ -206,20 +268,40 @@ fn get_status_color_discord(status: Status) -> u32 {
     }
 }
 
-/// Format payload for the configured preset
+/// `text` cut down to at most `max_len` bytes, on a word boundary with `...`
+pub fn fit_text(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    truncate_smart(text, max_len.saturating_sub(3))
+}
+
+/// Format payload for the configured preset. The summary is truncated to what
+/// the platform accepts (`WebhookPreset::max_text_len`)
 pub fn format_payload(
     preset: &WebhookPreset,
     status: Status,
     summary: &str,
     session_name: &str,
     chat_id: Option<&str>,
+    locale: &str,
 ) -> Result<String, String> {
+    let title = localized_status_title(status, locale);
+    let summary = match preset.max_text_len() {
+        // Telegram's limit covers the whole message, title and session included
+        Some(max) if *preset == WebhookPreset::Telegram => {
+            let frame = format!("<b>{}</b>\n\n<i>{}</i>", title, session_name).len();
+            fit_text(summary, max.saturating_sub(frame))
+        }
+        Some(max) => fit_text(summary, max),
+        None => summary.to_string(),
+    };
     match preset {
         WebhookPreset::Slack => {
             let payload = SlackPayload {
                 attachments: vec![SlackAttachment {
                     color: get_status_color_slack(status).to_string(),
-                    title: get_status_title(status).to_string(),
+                    title: title.clone(),
                     text: summary.to_string(),
                     footer: session_name.to_string(),
                 }],
@@ -230,7 +312,7 @@ pub fn format_payload(
         WebhookPreset::Discord => {
             let payload = DiscordPayload {
                 embeds: vec![DiscordEmbed {
-                    title: get_status_title(status).to_string(),
+                    title: title.clone(),
                     description: summary.to_string(),
                     color: get_status_color_discord(status),
                     footer: DiscordFooter {
@@ -242,7 +324,6 @@ pub fn format_payload(
                 .map_err(|e| format!("Failed to serialize Discord payload: {}", e))
         }
         WebhookPreset::Telegram => {
-            let title = get_status_title(status);
             let text = format!("<b>{}</b>\n{}\n<i>{}</i>", title, summary, session_name);
             let payload = TelegramPayload {
                 chat_id: chat_id.unwrap_or("").to_string(),
@@ -255,7 +336,7 @@ pub fn format_payload(
         WebhookPreset::Custom => {
             let payload = CustomPayload {
                 status: status.as_str().to_string(),
-                title: get_status_title(status).to_string(),
+                title: title.clone(),
                 message: summary.to_string(),
                 session: session_name.to_string(),
             };
@@ -265,6 +346,79 @@ pub fn format_payload(
     }
 }
 
+/// Render the payload the configured preset would send for `status`, using a
+/// sample summary, as pretty JSON
+pub fn preview_webhook(config: &Config, status: Status) -> Result<String, String> {
+    let webhook_config = &config.notifications.webhook;
+    let preset = WebhookPreset::from(webhook_config.preset.as_str());
+    let payload = format_payload(
+        &preset,
+        status,
+        "Refactored the config loader and all tests pass",
+        "[main] my-project (a1b2c3)",
+        webhook_config.telegram_chat_id.as_deref(),
+        &config.locale,
+    )?;
+    let value: serde_json::Value = serde_json::from_str(&payload).map_err(|e| e.to_string())?;
+    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+/// Run the `preview-webhook [status]` subcommand
+pub fn run_preview_webhook(config: &Config, args: &[String]) -> i32 {
+    let name = args.first().map(String::as_str).unwrap_or("task_complete");
+    let Some(status) = Status::from_name(name) else {
+        eprintln!("Unknown status '{}'. Use one of: task_complete, review_complete, question, plan_ready, session_limit_reached, api_error", name);
+        return 1;
+    };
+
+    let webhook_config = &config.notifications.webhook;
+    if let Some(warning) = preset_url_mismatch(&webhook_config.preset, &webhook_config.url) {
+        eprintln!("Warning: {}", warning);
+    }
+
+    match preview_webhook(config, status) {
+        Ok(json) => {
+            println!("Preset: {}", webhook_config.preset);
+            println!("{}", json);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Summary sent by `--test-webhook`
+pub const TEST_WEBHOOK_MESSAGE: &str = "permission-hook configured successfully";
+
+/// Run the `--test-webhook` command: send a test message through the configured
+/// webhook so the integration can be confirmed right after setting it up
+pub fn run_test_webhook(config: &Config) -> i32 {
+    if !config.notifications.webhook.enabled {
+        eprintln!("Webhooks are disabled (notifications.webhook.enabled is false)");
+        return 1;
+    }
+    let result = send_webhook(
+        config,
+        Status::TaskComplete,
+        TEST_WEBHOOK_MESSAGE,
+        "claude-permission-hook",
+        &mut CircuitBreaker::default(),
+        &mut RateLimiter::default(),
+    );
+    match result {
+        Ok(()) => {
+            println!("Test message sent via the {} webhook", config.notifications.webhook.preset);
+            0
+        }
+        Err(e) => {
+            eprintln!("Test webhook failed: {}", e);
+            1
+        }
+    }
+}
+
 /// Send webhook with retry logic
 pub fn send_webhook(
     config: &Config,
@@ -275,6 +429,34 @@ pub fn send_webhook(
     rate_limiter: &mut RateLimiter,
 ) -> Result<(), String> {
     let webhook_config = &config.notifications.webhook;
+    let max_attempts = if webhook_config.retry_enabled {
+        webhook_config.retry_max_attempts.max(1)
+    } else {
+        1
+    };
+    deliver_webhook(config, status, summary, session_name, circuit_breaker, rate_limiter, max_attempts, Duration::from_secs(10))
+}
+
+/// Send webhook once, giving up after `timeout`: for notifications sent while
+/// a tool call waits on the hook, which mustn't be held up by a dead endpoint
+pub fn send_webhook_once(config: &Config, status: Status, summary: &str, session_name: &str, timeout: Duration) -> Result<(), String> {
+    let mut circuit_breaker = CircuitBreaker::default();
+    let mut rate_limiter = RateLimiter::default();
+    deliver_webhook(config, status, summary, session_name, &mut circuit_breaker, &mut rate_limiter, 1, timeout)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn deliver_webhook(
+    config: &Config,
+    status: Status,
+    summary: &str,
+    session_name: &str,
+    circuit_breaker: &mut CircuitBreaker,
+    rate_limiter: &mut RateLimiter,
+    max_attempts: u32,
+    timeout: Duration,
+) -> Result<(), String> {
+    let webhook_config = &config.notifications.webhook;
 
     if !webhook_config.enabled {
         return Ok(());
@@ -284,6 +466,20 @@ pub fn send_webhook(
         return Err("Webhook URL not configured".to_string());
     }
 
+    // URL and chat_id may reference environment variables (${VAR})
+    let url = expand_env_vars(&webhook_config.url)?;
+    if let Some(warning) = preset_url_mismatch(&webhook_config.preset, &url) {
+        logging::warn(config, &warning);
+    }
+    let preset = WebhookPreset::from(webhook_config.preset.as_str());
+    let chat_id = match &webhook_config.telegram_chat_id {
+        Some(id) => Some(expand_env_vars(id)?),
+        None => None,
+    };
+    if preset == WebhookPreset::Telegram && chat_id.as_deref().map_or(true, |id| id.trim().is_empty()) {
+        return Err("Telegram webhook requires notifications.webhook.telegram_chat_id".to_string());
+    }
+
     // Check circuit breaker
     if circuit_breaker.is_open() {
         return Err("Circuit breaker is open".to_string());
@@ -294,18 +490,14 @@ pub fn send_webhook(
         return Err("Rate limit exceeded".to_string());
     }
 
-    let preset = WebhookPreset::from(webhook_config.preset.as_str());
-    let chat_id = webhook_config.telegram_chat_id.as_deref();
-    let payload = format_payload(&preset, status, summary, session_name, chat_id)?;
-
-    let max_attempts = if webhook_config.retry_enabled {
-        webhook_config.retry_max_attempts.max(1)
-    } else {
-        1
+    let summary = match webhook_config.max_summary_len {
+        0 => summary.to_string(),
+        max => fit_text(summary, max),
     };
+    let payload = format_payload(&preset, status, &summary, session_name, chat_id.as_deref(), &config.locale)?;
 
     let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(10))
+        .timeout(timeout)
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
@@ -319,7 +511,7 @@ pub fn send_webhook(
         }
 
         let result = client
-            .post(&webhook_config.url)
+            .post(&url)
             .header("Content-Type", "application/json")
             .body(payload.clone())
             .send();
@@ -430,6 +622,7 @@ mod tests {
             "Test message",
             "test-session",
             None,
+            "en",
         );
         assert!(result.is_ok());
         let json = result.unwrap();
@@ -445,6 +638,7 @@ mod tests {
             "Test message",
             "test-session",
             None,
+            "en",
         );
         assert!(result.is_ok());
         let json = result.unwrap();
@@ -460,6 +654,7 @@ mod tests {
             "Test message",
             "test-session",
             Some("123456"),
+            "en",
         );
         assert!(result.is_ok());
         let json = result.unwrap();
@@ -468,6 +663,37 @@ mod tests {
         assert!(json.contains("Plan Ready"));
     }
 
+    #[test]
+    fn test_format_payload_respects_platform_limits() {
+        let summary = "word ".repeat(2000);
+        let text_len = |preset: &WebhookPreset| {
+            let json = format_payload(preset, Status::TaskComplete, &summary, "session", Some("1"), "en").unwrap();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let text = match preset {
+                WebhookPreset::Slack => value["attachments"][0]["text"].clone(),
+                WebhookPreset::Discord => value["embeds"][0]["description"].clone(),
+                WebhookPreset::Telegram => value["text"].clone(),
+                WebhookPreset::Custom => value["message"].clone(),
+            };
+            let text = text.as_str().unwrap().to_string();
+            (text.len(), text)
+        };
+
+        for preset in [WebhookPreset::Slack, WebhookPreset::Discord, WebhookPreset::Telegram] {
+            let (len, text) = text_len(&preset);
+            let max = preset.max_text_len().unwrap();
+            assert!(len <= max, "{:?}: {} > {}", preset, len, max);
+            assert!(len > max - 100, "{:?} cut too short: {}", preset, len);
+            assert!(text.contains("..."), "{:?}", preset);
+        }
+        assert!(text_len(&WebhookPreset::Telegram).1.ends_with("<i>session</i>"));
+        // Custom endpoints get the summary as is
+        assert_eq!(text_len(&WebhookPreset::Custom).0, summary.len());
+
+        // Short summaries are untouched
+        assert_eq!(fit_text("done", 2000), "done");
+    }
+
     #[test]
     fn test_format_payload_custom() {
         let result = format_payload(
@@ -476,10 +702,124 @@ mod tests {
             "Test message",
             "test-session",
             None,
+            "en",
         );
         assert!(result.is_ok());
         let json = result.unwrap();
         assert!(json.contains("\"status\":\"task_complete\""));
+
+        // Titles are localized; the machine status code is not
+        let json = format_payload(&WebhookPreset::Custom, Status::TaskComplete, "msg", "s", None, "es").unwrap();
+        assert!(json.contains("Tarea completada"));
+        assert!(json.contains("\"status\":\"task_complete\""));
+    }
+
+    #[test]
+    fn test_preview_webhook_presets() {
+        let mut config = crate::config::default_config();
+        let expected: [(&str, &[&str]); 4] = [
+            ("slack", &["\"attachments\"", "\"color\": \"#ff9900\"", "\"title\"", "\"footer\": \"[main] my-project (a1b2c3)\""]),
+            ("discord", &["\"embeds\"", "\"color\": 16750848", "\"description\"", "\"footer\""]),
+            ("telegram", &["\"chat_id\"", "\"parse_mode\": \"HTML\"", "<b>❓ Question</b>"]),
+            ("custom", &["\"status\": \"question\"", "\"title\"", "\"message\"", "\"session\""]),
+        ];
+
+        for (preset, fields) in expected {
+            config.notifications.webhook.preset = preset.into();
+            let preview = preview_webhook(&config, Status::Question).unwrap();
+            for field in fields {
+                assert!(preview.contains(field), "{} preview missing {}: {}", preset, field, preview);
+            }
+        }
+    }
+
+    fn telegram_config(chat_id: Option<&str>) -> Config {
+        let mut config = crate::config::default_config();
+        config.notifications.webhook.enabled = true;
+        config.notifications.webhook.preset = "telegram".into();
+        config.notifications.webhook.url = "https://api.telegram.org/bot${PH_TEST_TG_TOKEN}/sendMessage".into();
+        config.notifications.webhook.telegram_chat_id = chat_id.map(String::from);
+        config
+    }
+
+    #[test]
+    fn test_telegram_requires_chat_id() {
+        std::env::set_var("PH_TEST_TG_TOKEN", "123:abc");
+        for chat_id in [None, Some(""), Some("  ")] {
+            let config = telegram_config(chat_id);
+            let err = send_webhook(&config, Status::TaskComplete, "s", "n", &mut CircuitBreaker::default(), &mut RateLimiter::default())
+                .unwrap_err();
+            assert!(err.contains("telegram_chat_id"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_webhook_env_resolution() {
+        // Unset variables are reported before anything is sent
+        let config = telegram_config(Some("${PH_TEST_TG_CHAT_UNSET}"));
+        std::env::set_var("PH_TEST_TG_TOKEN", "123:abc");
+        let err = send_webhook(&config, Status::TaskComplete, "s", "n", &mut CircuitBreaker::default(), &mut RateLimiter::default())
+            .unwrap_err();
+        assert!(err.contains("PH_TEST_TG_CHAT_UNSET"), "{}", err);
+
+        std::env::set_var("PH_TEST_TG_CHAT", "-100123");
+        assert_eq!(expand_env_vars("${PH_TEST_TG_CHAT}").unwrap(), "-100123");
+    }
+
+    #[test]
+    fn test_preset_url_mismatch() {
+        let slack_url = "https://hooks.slack.com/services/T000/B000/XXXX";
+        let discord_url = "https://discord.com/api/webhooks/123/abc";
+
+        let warning = preset_url_mismatch("slack", discord_url).unwrap();
+        assert!(warning.contains("'slack'") && warning.contains("discord"));
+        assert!(preset_url_mismatch("discord", slack_url).is_some());
+        assert!(preset_url_mismatch("custom", "https://api.telegram.org/bot1:x/sendMessage").is_some());
+        assert!(preset_url_mismatch("discord", "https://ptb.discordapp.com:443/api/webhooks/1/a").is_none());
+
+        // Matching presets and unknown hosts are fine
+        assert!(preset_url_mismatch("slack", slack_url).is_none());
+        assert!(preset_url_mismatch("discord", discord_url).is_none());
+        assert!(preset_url_mismatch("TELEGRAM", "https://api.telegram.org/bot1:x/sendMessage").is_none());
+        assert!(preset_url_mismatch("slack", "https://proxy.example.com/slack").is_none());
+        assert!(preset_url_mismatch("slack", "https://notslack.com/hook").is_none());
+    }
+
+    #[test]
+    fn test_run_test_webhook() {
+        let (url, server) = crate::test_http::serve(vec![Some((200, ""))]);
+        let url = format!("{}/hook", url);
+
+        let mut config = crate::config::default_config();
+        config.notifications.webhook.enabled = true;
+        config.notifications.webhook.preset = "custom".into();
+        config.notifications.webhook.url = url;
+        config.notifications.webhook.retry_enabled = false;
+        assert_eq!(run_test_webhook(&config), 0);
+
+        let body: serde_json::Value = serde_json::from_str(&server.join().unwrap()[0]).unwrap();
+        assert_eq!(body["message"], TEST_WEBHOOK_MESSAGE);
+        assert_eq!(body["session"], "claude-permission-hook");
+
+        // Nothing to test while disabled
+        config.notifications.webhook.enabled = false;
+        assert_eq!(run_test_webhook(&config), 1);
+    }
+
+    #[test]
+    fn test_send_webhook_once_gives_up_quickly() {
+        // The endpoint accepts the connection but never answers
+        let (url, server) = crate::test_http::serve(vec![None]);
+        let mut config = crate::config::default_config();
+        config.notifications.webhook.enabled = true;
+        config.notifications.webhook.preset = "custom".into();
+        config.notifications.webhook.url = url;
+
+        let started = std::time::Instant::now();
+        let result = send_webhook_once(&config, Status::Unknown, "git push", "session", Duration::from_millis(300));
+        assert!(result.unwrap_err().contains("after 1 attempts"));
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+        assert_eq!(server.join().unwrap().len(), 1);
     }
 
     #[test]